@@ -0,0 +1,89 @@
+//-----------------------------------------------------------------------------
+// Heuristic Benchmark
+//   Searches every puzzle of a corpus under each branching heuristic and
+//   prints how many guesses the search needed. Guess counts don't depend on the
+//   machine, so they compare heuristics more fairly than times do. Run with
+//   `cargo bench --bench heuristics`, optionally followed by `-- FILE` for a
+//   corpus with one puzzle per line instead of the built in one
+//-----------------------------------------------------------------------------
+
+use std::time::Duration;
+use clap::ValueEnum;
+use sudoku_solver::{GameBoard, Heuristic, SolveOptions, Status};
+use sudoku_solver::search::Budget;
+
+/// a handful of classic puzzles that need some guessing, and a 16x16
+const CORPUS: &[&str] = &[
+    "002000063009000001006000400020180070900760000070490816000800007300040008008000940",
+    "091000203000002700705600000000713060009000000000500002000007304000060009000300015",
+    "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+    "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+    "100007090030020008009600500005300900010080002600004000300000010040000007007000300",
+    "000000039000001005003050800008090006070002000100400000009080050020000600400700000",
+    "120400300300010050006000100700090000040603000003002000500080700007000005000000098",
+    "000003017015009008060000000100007000009000200000500004000000020500600340340200000",
+    "043080250600000000000001094900004070000608000010200003820500000000000005034090710",
+    "..9.F3....2..A....E.....1..8.0..8A.6D.C.....F....4.....1.A56..7....7..5.E....C1...C84F2....1....F1.3..D..6....8.2...8..C...40D.7E8..5..A.73.2.9B5..194...D............7......3.....4..B8.0C5D....D7..6.E0...5...9..52..083.E.B.D.2..B..3F....4...B..A7.......E..",
+];
+
+const MAX_NODES: usize = 100_000;
+
+fn main() {
+    let corpus: Vec<String> = match std::env::args().skip(1).find(|arg| !arg.starts_with("--")) {
+        Some(path) => std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("Can't read {}: {}", path, e))
+            .lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).map(String::from).collect(),
+        None => CORPUS.iter().map(|puzzle| puzzle.to_string()).collect(),
+    };
+    let heuristics = Heuristic::value_variants();
+    let names: Vec<String> = heuristics.iter().map(|h| h.to_possible_value().unwrap().get_name().to_string()).collect();
+
+    print!("{:>7}", "puzzle");
+    for name in &names {
+        print!(" {:>18}", name);
+    }
+    println!();
+    let mut totals = vec![0; heuristics.len()];
+    let mut times = vec![Duration::ZERO; heuristics.len()];
+    let mut gave_up = vec![0; heuristics.len()];
+    for (n, puzzle) in corpus.iter().enumerate() {
+        print!("{:>7}", n+1);
+        for (k, &heuristic) in heuristics.iter().enumerate() {
+            let mut board = GameBoard::new();
+            board.init_board_from_string(puzzle.clone());
+            let mut budget = Budget::new(SolveOptions{max_nodes:Some(MAX_NODES), heuristic, ..SolveOptions::default()});
+            let found = board.search_clone().search(1, |_| {}, &mut budget);
+            let status = match budget.stopped {
+                Some(limit) => Status::GaveUp(limit),
+                None if found == 0 => Status::NoSolution,
+                None => Status::Solved,
+            };
+            let result = budget.finish(status);
+            totals[k] += result.stats.nodes;
+            times[k] += result.stats.elapsed;
+            match result.status {
+                Status::Solved => print!(" {:>18}", result.stats.nodes),
+                Status::NoSolution => print!(" {:>18}", "no solution"),
+                Status::GaveUp(_) => {
+                    gave_up[k] += 1;
+                    print!(" {:>18}", format!(">{}", MAX_NODES));
+                }
+            }
+        }
+        println!();
+    }
+    print!("{:>7}", "total");
+    for total in &totals {
+        print!(" {:>18}", total);
+    }
+    println!();
+    print!("{:>7}", "time");
+    for time in &times {
+        print!(" {:>17.2}s", time.as_secs_f64());
+    }
+    println!();
+    print!("{:>7}", "gave up");
+    for count in &gave_up {
+        print!(" {:>18}", count);
+    }
+    println!();
+}
@@ -0,0 +1,48 @@
+//-----------------------------------------------------------------------------
+// Custom Rule Example
+//   A house rule written outside the solver: the four corner cells hold
+//   different values. Run with `cargo run --example custom_rule`
+//-----------------------------------------------------------------------------
+
+use sudoku_solver::{Changed, Constraint, Contradiction, GameBoard};
+
+#[derive(Debug)]
+struct DistinctCorners;
+
+impl Constraint for DistinctCorners {
+    fn propagate(&self, board:&mut GameBoard) -> Result<Changed,Contradiction> {
+        let last = board.shape.size - 1;
+        let corners = [(0,0), (0,last), (last,0), (last,last)];
+        let placed: Vec<usize> = corners.iter().map(|&c| board.options(c)).filter(|options| options.len() == 1).map(|options| options[0]).collect();
+        let mut seen = placed.clone();
+        seen.sort();
+        seen.dedup();
+        if seen.len() != placed.len() {
+            return Err(Contradiction("two corners hold the same value".to_string()));
+        }
+        // a corner down to one option keeps that value out of the other corners
+        let allowed: Vec<Vec<usize>> = corners.iter().map(|&c| {
+            let options = board.options(c);
+            if options.len() == 1 {options} else {options.into_iter().filter(|v| !placed.contains(v)).collect()}
+        }).collect();
+        let (changed, _) = board.restrict(&corners, &allowed);
+        Ok(!changed.is_empty())
+    }
+
+    fn explain(&self) -> Option<String> {
+        Some("the four corners hold different values".to_string())
+    }
+}
+
+fn main() {
+    let mut board = GameBoard::new();
+    board.init_board_from_string("002000063009000001006000400020180070900760000070490816000800007300040008008000940".to_string());
+    board.add_constraint(DistinctCorners);
+    if !board.has_unique_solution() {
+        println!("the corner rule doesn't leave exactly one solution");
+    }
+    board.solve(false);
+    for step in board.steps.iter().filter(|s| s.technique == sudoku_solver::steps::Technique::Custom) {
+        println!("{}", step);
+    }
+}
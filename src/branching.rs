@@ -0,0 +1,83 @@
+//-----------------------------------------------------------------------------
+// Branching Heuristics
+//   When propagation gets stuck the search has to guess. A heuristic picks
+//   what to guess on and in which order to try the alternatives. Guessing
+//   on a cell tries each of its possible values, guessing on a digit in a
+//   unit tries each cell of the unit the digit can still go in. Either way
+//   exactly one of the alternatives is right, so the search stays complete
+//-----------------------------------------------------------------------------
+
+use std::ops::Deref;
+use crate::GameBoard;
+use crate::random::Rng;
+
+/// Placements of a value in a cell. A guess tries them in order
+pub type Branch = Vec<((usize,usize),usize)>;
+
+/// How the search picks what to guess
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq,clap::ValueEnum)]
+pub enum Heuristic {
+    #[default]
+    Smallest, // the first cell with the fewest possible values, trying them in ascending order
+    MrvDegree, // the cell with the fewest possible values, ties going to the one seeing the most unsolved cells
+    DigitInUnit, // the digit with the fewest places left in a unit, trying each place
+    LeastConstraining, // the first cell with the fewest possible values, trying first the values the fewest unsolved peers could also be
+    Random, // a random cell among those with the fewest possible values, trying them in a random order
+}
+
+impl GameBoard {
+    /// what to guess next under the heuristic. Empty when the board is solved
+    pub fn branch(&self, heuristic:Heuristic, rng:&mut Rng) -> Branch {
+        let cell = match heuristic {
+            Heuristic::Smallest | Heuristic::LeastConstraining => self.get_smallest_possible_gamecell_by_idx(),
+            Heuristic::MrvDegree => self.fewest_values_cells().into_iter().max_by_key(|&cell| (self.unsolved_peers(cell), std::cmp::Reverse(cell))),
+            Heuristic::Random => {
+                let cells = self.fewest_values_cells();
+                (!cells.is_empty()).then(|| cells[rng.below(cells.len())])
+            }
+            Heuristic::DigitInUnit => return self.digit_in_unit_branch(),
+        };
+        let Some((i,j)) = cell else {return Vec::new()};
+        let mut values = self.board[i][j].borrow().possible_values.clone();
+        match heuristic {
+            Heuristic::LeastConstraining => values.sort_by_key(|&v| self.peers_allowing((i,j), v)),
+            Heuristic::Random => rng.shuffle(&mut values),
+            _ => {}
+        }
+        values.into_iter().map(|v| ((i,j),v)).collect()
+    }
+
+    /// every unsolved cell tied for the fewest possible values, in board order
+    fn fewest_values_cells(&self) -> Vec<(usize,usize)> {
+        let Some((i,j)) = self.get_smallest_possible_gamecell_by_idx() else {return Vec::new()};
+        let smallest = self.board[i][j].borrow().possible_values.len();
+        self.shape.cells().filter(|&(i,j)| self.board[i][j].borrow().deref().possible_values.len() == smallest).collect()
+    }
+
+    /// how many unsolved cells see the cell
+    fn unsolved_peers(&self, cell:(usize,usize)) -> usize {
+        self.shape.peers(cell).iter().filter(|&&(i,j)| self.board[i][j].borrow().value == 0).count()
+    }
+
+    /// how many unsolved cells that see the cell could also hold the value, so would lose it if the cell took it
+    fn peers_allowing(&self, cell:(usize,usize), v:usize) -> usize {
+        self.shape.peers(cell).iter().filter(|&&(i,j)| self.board[i][j].borrow().possible_values.contains(&v)).count()
+    }
+
+    /// the unplaced digit with the fewest places left in any unit, trying each place in board order
+    fn digit_in_unit_branch(&self) -> Branch {
+        let mut best: Option<Branch> = None;
+        for unit in self.shape.units() {
+            let cells = self.shape.unit_cells(unit);
+            for v in 1..=self.shape.size {
+                if cells.iter().any(|&(i,j)| self.board[i][j].borrow().value == v) {continue;}
+                let places: Branch = cells.iter().filter(|&&(i,j)| self.board[i][j].borrow().possible_values.contains(&v))
+                    .map(|&cell| (cell,v)).collect();
+                if best.as_ref().is_none_or(|best| places.len() < best.len()) {
+                    best = Some(places);
+                }
+            }
+        }
+        best.unwrap_or_default()
+    }
+}
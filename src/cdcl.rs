@@ -0,0 +1,471 @@
+//-----------------------------------------------------------------------------
+// CDCL SAT Solver
+//   A small conflict driven clause learning solver for the CNF encoding of
+//   a puzzle. Clauses are watched on two literals, conflicts are analysed
+//   back to the first unique implication point and the learnt clause sends
+//   the search back to the level where it becomes a unit. Variables are
+//   picked by activity, bumped each time they take part in a conflict, and
+//   the search restarts on the Luby sequence keeping each variable's last
+//   value. Learnt clauses are never deleted, which is fine at puzzle sizes
+//-----------------------------------------------------------------------------
+
+/// A literal is twice its zero based variable, plus one when negated
+type Lit = usize;
+
+fn lit(dimacs:i32) -> Lit {
+    let var = dimacs.unsigned_abs() as usize - 1;
+    2*var + (dimacs < 0) as usize
+}
+
+fn var(lit:Lit) -> usize {
+    lit/2
+}
+
+fn negate(lit:Lit) -> Lit {
+    lit ^ 1
+}
+
+/// The number of conflicts between restarts is this many times the Luby sequence
+const RESTART_UNIT: u64 = 100;
+/// How much the activity bump grows after every conflict, so recent conflicts count for more
+const ACTIVITY_DECAY: f64 = 0.95;
+
+/// The unassigned variables, kept in a binary heap by activity so the most active one is found quickly
+struct VarOrder {
+    heap: Vec<usize>,
+    position: Vec<Option<usize>>, // where each variable is in the heap, None when it isn't
+}
+impl VarOrder {
+    fn new(variables:usize) -> VarOrder {
+        VarOrder{heap:(0..variables).collect(), position:(0..variables).map(Some).collect()}
+    }
+
+    fn insert(&mut self, v:usize, activity:&[f64]) {
+        if self.position[v].is_none() {
+            self.position[v] = Some(self.heap.len());
+            self.heap.push(v);
+            self.up(self.heap.len()-1, activity);
+        }
+    }
+
+    /// moves a variable up after its activity grew
+    fn bumped(&mut self, v:usize, activity:&[f64]) {
+        if let Some(k) = self.position[v] {
+            self.up(k, activity);
+        }
+    }
+
+    fn pop(&mut self, activity:&[f64]) -> Option<usize> {
+        let top = *self.heap.first()?;
+        let last = self.heap.pop().unwrap();
+        self.position[top] = None;
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.position[last] = Some(0);
+            self.down(0, activity);
+        }
+        Some(top)
+    }
+
+    fn up(&mut self, mut k:usize, activity:&[f64]) {
+        while k > 0 && activity[self.heap[(k-1)/2]] < activity[self.heap[k]] {
+            self.swap(k, (k-1)/2);
+            k = (k-1)/2;
+        }
+    }
+
+    fn down(&mut self, mut k:usize, activity:&[f64]) {
+        loop {
+            let mut largest = k;
+            for child in [2*k+1, 2*k+2] {
+                if child < self.heap.len() && activity[self.heap[child]] > activity[self.heap[largest]] {
+                    largest = child;
+                }
+            }
+            if largest == k {
+                return;
+            }
+            self.swap(k, largest);
+            k = largest;
+        }
+    }
+
+    fn swap(&mut self, a:usize, b:usize) {
+        self.heap.swap(a, b);
+        self.position[self.heap[a]] = Some(a);
+        self.position[self.heap[b]] = Some(b);
+    }
+}
+
+/// Counts of the work done by the solver
+#[derive(Clone,Copy,Debug,Default)]
+pub struct Stats {
+    pub decisions: u64,
+    pub propagations: u64,
+    pub conflicts: u64,
+    pub restarts: u64,
+}
+
+pub struct Solver {
+    clauses: Vec<Vec<Lit>>, // the first two literals of each clause are the ones watched
+    watches: Vec<Vec<usize>>, // the clauses watching each literal, visited when it becomes false
+    values: Vec<Option<bool>>, // the value of each variable
+    level: Vec<usize>, // the decision level each variable was set at
+    reason: Vec<Option<usize>>, // the clause that forced each variable, with the forced literal first, None for decisions
+    trail: Vec<Lit>, // every true literal in the order it was set
+    trail_limits: Vec<usize>, // where each decision level starts on the trail
+    queue_head: usize, // the first literal on the trail that hasn't been propagated
+    activity: Vec<f64>,
+    bump: f64,
+    order: VarOrder,
+    phase: Vec<bool>, // the last value of each variable, reused when it is next picked
+    seen: Vec<bool>, // scratch space for conflict analysis
+    unsatisfiable: bool, // set once a conflict is found without any decisions
+    pub stats: Stats,
+}
+impl Solver {
+    pub fn new(variables:usize) -> Solver {
+        Solver{
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2*variables],
+            values: vec![None; variables],
+            level: vec![0; variables],
+            reason: vec![None; variables],
+            trail: Vec::new(),
+            trail_limits: Vec::new(),
+            queue_head: 0,
+            activity: vec![0.0; variables],
+            bump: 1.0,
+            order: VarOrder::new(variables),
+            phase: vec![false; variables],
+            seen: vec![false; variables],
+            unsatisfiable: false,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Adds a clause written with DIMACS literals, 1 based variables negated for false. Can be called between solves
+    pub fn add_clause(&mut self, clause:&[i32]) {
+        self.backtrack(0);
+        let mut lits: Vec<Lit> = clause.iter().map(|&l| lit(l)).collect();
+        lits.sort();
+        lits.dedup();
+        if lits.windows(2).any(|pair| pair[1] == negate(pair[0])) || lits.iter().any(|&l| self.value(l) == Some(true)) {
+            return;
+        }
+        lits.retain(|&l| self.value(l) != Some(false));
+        match lits.len() {
+            0 => {
+                self.unsatisfiable = true;
+            }
+            1 => {
+                self.assign(lits[0], None);
+                if self.propagate().is_some() {
+                    self.unsatisfiable = true;
+                }
+            }
+            _ => {
+                self.attach(lits);
+            }
+        }
+    }
+
+    /// the value of a literal under the current assignment
+    fn value(&self, lit:Lit) -> Option<bool> {
+        self.values[var(lit)].map(|v| v != (lit & 1 == 1))
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_limits.len()
+    }
+
+    /// stores a clause of two or more literals and watches its first two. Returns its index
+    fn attach(&mut self, lits:Vec<Lit>) -> usize {
+        let index = self.clauses.len();
+        self.watches[lits[0]].push(index);
+        self.watches[lits[1]].push(index);
+        self.clauses.push(lits);
+        index
+    }
+
+    fn assign(&mut self, lit:Lit, reason:Option<usize>) {
+        let v = var(lit);
+        self.values[v] = Some(lit & 1 == 0);
+        self.level[v] = self.decision_level();
+        self.reason[v] = reason;
+        self.trail.push(lit);
+    }
+
+    /// sets every literal forced by unit clauses. Returns the clause that became false, if there is one
+    fn propagate(&mut self) -> Option<usize> {
+        while self.queue_head < self.trail.len() {
+            let false_lit = negate(self.trail[self.queue_head]);
+            self.queue_head += 1;
+            self.stats.propagations += 1;
+            let watching = std::mem::take(&mut self.watches[false_lit]);
+            let mut kept = Vec::with_capacity(watching.len());
+            let mut conflict = None;
+            for (n,&c) in watching.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend_from_slice(&watching[n..]);
+                    break;
+                }
+                if self.clauses[c][0] == false_lit {
+                    self.clauses[c].swap(0, 1);
+                }
+                let first = self.clauses[c][0];
+                if self.value(first) == Some(true) {
+                    kept.push(c);
+                    continue;
+                }
+                // look for another literal to watch that isn't false
+                if let Some(k) = (2..self.clauses[c].len()).find(|&k| self.value(self.clauses[c][k]) != Some(false)) {
+                    self.clauses[c].swap(1, k);
+                    self.watches[self.clauses[c][1]].push(c);
+                    continue;
+                }
+                kept.push(c);
+                if self.value(first) == Some(false) {
+                    conflict = Some(c);
+                } else {
+                    self.assign(first, Some(c));
+                }
+            }
+            self.watches[false_lit] = kept;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    /// works back from a conflict to the first literal of the current level that all of it passes through,
+    /// returning the learnt clause with that literal negated first and the level to go back to
+    fn analyse(&mut self, mut conflict:usize) -> (Vec<Lit>, usize) {
+        let mut learnt: Vec<Lit> = vec![0];
+        let mut open = 0; // literals of the current level still to work through
+        let mut index = self.trail.len();
+        let mut forced: Option<Lit> = None;
+        loop {
+            // the literal a reason clause forced is first in it and already handled
+            let skip = if forced.is_some() {1} else {0};
+            for k in skip..self.clauses[conflict].len() {
+                let q = self.clauses[conflict][k];
+                let v = var(q);
+                if !self.seen[v] && self.level[v] > 0 {
+                    self.seen[v] = true;
+                    self.bump_activity(v);
+                    if self.level[v] == self.decision_level() {
+                        open += 1;
+                    } else {
+                        learnt.push(q);
+                    }
+                }
+            }
+            loop {
+                index -= 1;
+                if self.seen[var(self.trail[index])] {
+                    break;
+                }
+            }
+            let p = self.trail[index];
+            self.seen[var(p)] = false;
+            open -= 1;
+            if open == 0 {
+                learnt[0] = negate(p);
+                break;
+            }
+            forced = Some(p);
+            conflict = self.reason[var(p)].expect("only the first literal of a level is a decision");
+        }
+        for &q in &learnt[1..] {
+            self.seen[var(q)] = false;
+        }
+        // watch the literal from the highest remaining level second so the clause wakes up as soon as it is undone
+        let mut back_to = 0;
+        if learnt.len() > 1 {
+            let highest = (1..learnt.len()).max_by_key(|&k| self.level[var(learnt[k])]).unwrap();
+            learnt.swap(1, highest);
+            back_to = self.level[var(learnt[1])];
+        }
+        (learnt, back_to)
+    }
+
+    fn bump_activity(&mut self, v:usize) {
+        self.activity[v] += self.bump;
+        if self.activity[v] > 1e100 {
+            for a in &mut self.activity {
+                *a *= 1e-100;
+            }
+            self.bump *= 1e-100;
+        }
+        self.order.bumped(v, &self.activity);
+    }
+
+    /// undoes every assignment above a decision level
+    fn backtrack(&mut self, level:usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let start = self.trail_limits[level];
+        for &l in &self.trail[start..] {
+            self.phase[var(l)] = l & 1 == 0;
+            self.values[var(l)] = None;
+            self.order.insert(var(l), &self.activity);
+        }
+        self.trail.truncate(start);
+        self.trail_limits.truncate(level);
+        self.queue_head = start;
+    }
+
+    /// the unassigned variable with the highest activity
+    fn pick(&mut self) -> Option<usize> {
+        while let Some(v) = self.order.pop(&self.activity) {
+            if self.values[v].is_none() {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    /// Searches for an assignment that makes every clause true. Returns the value of each variable, 1 based
+    /// like DIMACS with index 0 unused, or None if there isn't one
+    pub fn solve(&mut self) -> Option<Vec<bool>> {
+        if self.unsatisfiable {
+            return None;
+        }
+        let mut restarts = 0;
+        let mut budget = luby(restarts)*RESTART_UNIT;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.stats.conflicts += 1;
+                if self.decision_level() == 0 {
+                    self.unsatisfiable = true;
+                    return None;
+                }
+                let (learnt, back_to) = self.analyse(conflict);
+                self.backtrack(back_to);
+                let asserting = learnt[0];
+                if learnt.len() == 1 {
+                    self.assign(asserting, None);
+                } else {
+                    let c = self.attach(learnt);
+                    self.assign(asserting, Some(c));
+                }
+                self.bump /= ACTIVITY_DECAY;
+                budget = budget.saturating_sub(1);
+                continue;
+            }
+            if budget == 0 {
+                restarts += 1;
+                self.stats.restarts += 1;
+                budget = luby(restarts)*RESTART_UNIT;
+                self.backtrack(0);
+                continue;
+            }
+            let Some(v) = self.pick() else {
+                let mut model = vec![false];
+                model.extend(self.values.iter().map(|v| v == &Some(true)));
+                return Some(model);
+            };
+            self.stats.decisions += 1;
+            self.trail_limits.push(self.trail.len());
+            self.assign(2*v + (!self.phase[v]) as usize, None);
+        }
+    }
+}
+
+/// The Luby sequence 1 1 2 1 1 2 4 1 1 2 ..., starting from index 0
+fn luby(mut index:u64) -> u64 {
+    let mut size = 1;
+    let mut power = 1;
+    while size < index + 1 {
+        size = 2*size + 1;
+        power *= 2;
+    }
+    while size - 1 != index {
+        size = (size - 1)/2;
+        power /= 2;
+        index %= size;
+    }
+    power
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solver(variables:usize, clauses:&[&[i32]]) -> Solver {
+        let mut solver = Solver::new(variables);
+        for clause in clauses {
+            solver.add_clause(clause);
+        }
+        solver
+    }
+
+    fn satisfies(model:&[bool], clauses:&[&[i32]]) -> bool {
+        clauses.iter().all(|clause| clause.iter().any(|&l| model[l.unsigned_abs() as usize] == (l > 0)))
+    }
+
+    /// every pigeon in a hole and no two pigeons in the same hole. Variable p*holes + h + 1 puts pigeon p in hole h
+    fn pigeonhole(pigeons:usize, holes:usize) -> Vec<Vec<i32>> {
+        let var = |p:usize, h:usize| (p*holes + h + 1) as i32;
+        let mut clauses: Vec<Vec<i32>> = (0..pigeons).map(|p| (0..holes).map(|h| var(p, h)).collect()).collect();
+        for h in 0..holes {
+            for p in 0..pigeons {
+                for q in p+1..pigeons {
+                    clauses.push(vec![-var(p, h), -var(q, h)]);
+                }
+            }
+        }
+        clauses
+    }
+
+    #[test]
+    fn satisfiable() {
+        let clauses: &[&[i32]] = &[&[1, 2], &[-1, 2], &[-2, 3], &[-3, -4, 1]];
+        let model = solver(4, clauses).solve().unwrap();
+        assert_eq!(model.len(), 5);
+        assert!(satisfies(&model, clauses));
+        assert!(model[2] && model[3]);
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        assert!(solver(1, &[&[1], &[-1]]).solve().is_none());
+        assert!(solver(2, &[&[1, 2], &[-1, 2], &[1, -2], &[-1, -2]]).solve().is_none());
+        assert!(solver(1, &[&[]]).solve().is_none());
+    }
+
+    #[test]
+    fn pigeons_need_enough_holes() {
+        let fits = pigeonhole(5, 5);
+        let clauses: Vec<&[i32]> = fits.iter().map(|c| c.as_slice()).collect();
+        assert!(satisfies(&solver(25, &clauses).solve().unwrap(), &clauses));
+
+        let crowded = pigeonhole(6, 5);
+        let clauses: Vec<&[i32]> = crowded.iter().map(|c| c.as_slice()).collect();
+        let mut solver = solver(30, &clauses);
+        assert!(solver.solve().is_none());
+        assert!(solver.stats.conflicts > 0);
+    }
+
+    #[test]
+    fn blocking_clauses_count_the_models() {
+        // exactly one of three variables is true
+        let mut solver = solver(3, &[&[1, 2, 3], &[-1, -2], &[-1, -3], &[-2, -3]]);
+        let mut models = 0;
+        while let Some(model) = solver.solve() {
+            models += 1;
+            let blocking: Vec<i32> = (1..=3).map(|v| if model[v] {-(v as i32)} else {v as i32}).collect();
+            solver.add_clause(&blocking);
+        }
+        assert_eq!(models, 3);
+    }
+
+    #[test]
+    fn luby_sequence() {
+        let sequence: Vec<u64> = (0..15).map(luby).collect();
+        assert_eq!(sequence, vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+}
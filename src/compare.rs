@@ -0,0 +1,130 @@
+//-----------------------------------------------------------------------------
+// Even/Odd Cells and Greater-Than Signs
+//   Shaded cells hold only even or only odd values. A greater-than sign
+//   between two neighbouring cells points at the smaller value, so the
+//   bigger cell's lowest value is above the smaller cell's lowest value and
+//   the smaller cell's highest value is below the bigger cell's highest
+//   value. Applying that to every sign until nothing changes carries the
+//   bounds along chains of signs without any guessing
+//-----------------------------------------------------------------------------
+
+use std::rc::Rc;
+use crate::GameBoard;
+use crate::steps::{cell_list, cell_name, digit_list, Step, Technique};
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Parity {
+    Even,
+    Odd,
+}
+impl Parity {
+    pub fn allows(&self, value:usize) -> bool {
+        match self {
+            Parity::Even => value.is_multiple_of(2),
+            Parity::Odd => !value.is_multiple_of(2),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Parity::Even => "even",
+            Parity::Odd => "odd",
+        }
+    }
+}
+
+/// A greater-than sign between two neighbouring cells, the bigger one first
+#[derive(Clone,Debug)]
+pub struct Sign {
+    pub cells: [(usize,usize); 2],
+}
+impl Sign {
+    pub fn new(bigger:(usize,usize), smaller:(usize,usize)) -> Result<Sign,String> {
+        if bigger.0.abs_diff(smaller.0) + bigger.1.abs_diff(smaller.1) != 1 {
+            return Err(format!("{} and {} are not next to each other", cell_name(bigger), cell_name(smaller)));
+        }
+        Ok(Sign{cells:[bigger,smaller]})
+    }
+
+    /// The character drawn between the two cells, pointing at the smaller one
+    pub fn symbol(&self) -> char {
+        let [bigger, smaller] = self.cells;
+        match (bigger.0 == smaller.0, bigger < smaller) {
+            (true, true) => '>',
+            (true, false) => '<',
+            (false, true) => 'v',
+            (false, false) => '^',
+        }
+    }
+
+    /// True if the sign sits between the two cells, in either order
+    pub fn joins(&self, a:(usize,usize), b:(usize,usize)) -> bool {
+        self.cells == [a,b] || self.cells == [b,a]
+    }
+}
+
+impl GameBoard {
+    /// removes the values of the wrong parity from every shaded cell
+    pub fn set_possible_values_by_parity(&mut self) {
+        self.filter_parity(Parity::Even);
+        self.filter_parity(Parity::Odd);
+    }
+
+    /// removes the odd values from the cells shaded even, or the even ones from the cells shaded odd. Boolean return value indicates changes were made
+    pub fn filter_parity(&mut self, parity:Parity) -> bool {
+        let cells: Vec<(usize,usize)> = self.constraints.parities.iter().filter(|(_,p)| *p == parity).map(|&(c,_)| c).collect();
+        let allowed: Vec<Vec<usize>> = cells.iter().map(|&(i,j)| {
+            self.board[i][j].borrow().possible_values.iter().copied().filter(|&v| parity.allows(v)).collect()
+        }).collect();
+        let (changed, removed) = self.restrict(&cells, &allowed);
+        if changed.is_empty() {
+            return false;
+        }
+        if self.record_steps {
+            let reason = format!("{} are shaded {}, so {} are removed from {}", cell_list(&cells), parity.name(), digit_list(&removed), cell_list(&changed));
+            self.steps.push(Step::new(Technique::Parity, changed, removed, reason));
+        }
+        true
+    }
+
+    /// tightens the bounds across every greater-than sign until none of them change
+    pub fn set_possible_values_by_signs(&mut self) {
+        loop {
+            let mut changed = false;
+            for k in 0..self.constraints.signs.len() {
+                changed |= self.filter_sign(k);
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// the bigger cell of a sign is at least one more than the smaller cell's lowest value, and the smaller cell at
+    /// least one less than the bigger cell's highest value. Boolean return value indicates changes were made
+    pub fn filter_sign(&mut self, k:usize) -> bool {
+        let constraints = Rc::clone(&self.constraints);
+        let [bigger, smaller] = constraints.signs[k].cells;
+        let (Some(high), Some(low)) = (self.bounds(bigger), self.bounds(smaller)) else {
+            return false;
+        };
+        let bounds = [(high.0.max(low.0 + 1), high.1), (low.0, low.1.min(high.1.saturating_sub(1)))];
+        let description = format!("{} is greater than {}", cell_name(bigger), cell_name(smaller));
+        self.restrict_bounds(&[bigger, smaller], &bounds, Technique::GreaterThan, description)
+    }
+
+    /// the greater-than sign between two cells, if there is one
+    pub fn sign_between(&self, a:(usize,usize), b:(usize,usize)) -> Option<char> {
+        self.constraints.signs.iter().find(|sign| sign.joins(a, b)).map(|sign| sign.symbol())
+    }
+
+    /// check to see if any value has the wrong parity for its cell, or any sign points the wrong way
+    pub fn has_compare_conflicts(&self) -> bool {
+        let value = |(i,j):(usize,usize)| self.board[i][j].borrow().value;
+        self.constraints.parities.iter().any(|&(c,parity)| value(c) != 0 && !parity.allows(value(c))) ||
+            self.constraints.signs.iter().any(|sign| {
+                let [x, y] = sign.cells.map(value);
+                x != 0 && y != 0 && x <= y
+            })
+    }
+}
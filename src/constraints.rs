@@ -0,0 +1,235 @@
+//-----------------------------------------------------------------------------
+// Constraint Files
+//   Variant rules that don't fit in the board string are read from a text
+//   file with one constraint per line, a keyword followed by its arguments:
+//
+//     # a killer cage adding up to 15
+//     cage 15 r1c1 r1c2 r2c1
+//     # Kropki dots between two neighbouring cells
+//     white r1c1 r1c2
+//     black r2c1 r3c1
+//     # no dot means the neighbours are neither one apart nor double
+//     kropki-negative
+//     # neighbours without a white dot aren't one apart
+//     nonconsecutive
+//     # a thermometer from its bulb to its tip
+//     thermo r4c1 r5c2 r6c2
+//     # an arrow from its circle along its cells
+//     arrow r9c9 r8c8 r7c8
+//     # an X between neighbours adding up to 10, and a V for 5
+//     x r1c1 r1c2
+//     v r5c5 r6c5
+//     # the values between 1 and 9 in row 3 add up to 15
+//     sandwich row 3 15
+//     # the diagonal from r1c2 down to the right adds up to 23, also dl, ur, and ul
+//     little-killer 23 r1c2 dr
+//     # German whispers, renban, and palindrome lines through touching cells
+//     whisper r1c1 r2c2 r3c2
+//     renban r7c1 r7c2 r8c3
+//     palindrome r4c4 r4c5 r5c6 r6c6
+//     # cells shaded for even or odd values
+//     even r1c1 r9c9
+//     odd r5c5
+//     # a greater-than sign pointing from r2c2 at the smaller r2c3
+//     greater r2c2 r2c3
+//
+//   Cells are written r<row>c<col> with one based indexes. Blank lines and
+//   anything after a '#' are ignored
+//-----------------------------------------------------------------------------
+
+use std::path::Path;
+use std::rc::Rc;
+use crate::custom::Constraint;
+use crate::compare::{Parity, Sign};
+use crate::dots::{Dot, DotKind};
+use crate::killer::Cage;
+use crate::lines::{Arrow, Line, LineKind, Thermo};
+use crate::outside::{Direction, LittleKiller, Sandwich};
+use crate::shape::{Shape, Unit};
+
+/// Every constraint read from a constraint file
+#[derive(Clone,Debug,Default)]
+pub struct Constraints {
+    pub cages: Vec<Cage>,
+    pub dots: Vec<Dot>,
+    pub kropki_negative: bool, // neighbours without a dot are neither one apart nor double
+    pub nonconsecutive: bool, // neighbours without a white dot aren't one apart
+    pub thermos: Vec<Thermo>,
+    pub arrows: Vec<Arrow>,
+    pub lines: Vec<Line>, // German whispers, renban, and palindromes
+    pub sandwiches: Vec<Sandwich>,
+    pub little_killers: Vec<LittleKiller>,
+    pub parities: Vec<((usize,usize), Parity)>, // cells shaded even or odd
+    pub signs: Vec<Sign>,
+    pub custom: Vec<Rc<dyn Constraint>>, // rules added with GameBoard::add_constraint rather than read from the file
+}
+impl Constraints {
+    /// Reads the constraints from the text of a constraint file
+    pub fn parse(text:&str, shape:&Shape) -> Result<Constraints,String> {
+        let mut returned = Constraints::default();
+        for (n,line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            let result = match words[0] {
+                "cage" => parse_cage(&words[1..], shape).map(|cage| returned.cages.push(cage)),
+                "white" => parse_dot(&words[1..], DotKind::White, shape).map(|dot| returned.dots.push(dot)),
+                "black" => parse_dot(&words[1..], DotKind::Black, shape).map(|dot| returned.dots.push(dot)),
+                "kropki-negative" => parse_flag(&words[1..]).map(|_| returned.kropki_negative = true),
+                "nonconsecutive" => parse_flag(&words[1..]).map(|_| returned.nonconsecutive = true),
+                "thermo" => parse_cells(&words[1..], shape).and_then(|cells| Thermo::new(cells, shape.size)).map(|thermo| returned.thermos.push(thermo)),
+                "arrow" => parse_arrow(&words[1..], shape).map(|arrow| returned.arrows.push(arrow)),
+                "whisper" => parse_line(&words[1..], LineKind::Whisper, shape).map(|line| returned.lines.push(line)),
+                "renban" => parse_line(&words[1..], LineKind::Renban, shape).map(|line| returned.lines.push(line)),
+                "palindrome" => parse_line(&words[1..], LineKind::Palindrome, shape).map(|line| returned.lines.push(line)),
+                "x" => parse_dot(&words[1..], DotKind::X, shape).map(|dot| returned.dots.push(dot)),
+                "v" => parse_dot(&words[1..], DotKind::V, shape).map(|dot| returned.dots.push(dot)),
+                "sandwich" => parse_sandwich(&words[1..], shape).map(|sandwich| returned.sandwiches.push(sandwich)),
+                "little-killer" => parse_little_killer(&words[1..], shape).map(|lk| returned.little_killers.push(lk)),
+                "even" => parse_cells(&words[1..], shape).map(|cells| returned.parities.extend(cells.into_iter().map(|c| (c, Parity::Even)))),
+                "odd" => parse_cells(&words[1..], shape).map(|cells| returned.parities.extend(cells.into_iter().map(|c| (c, Parity::Odd)))),
+                "greater" => parse_sign(&words[1..], shape).map(|sign| returned.signs.push(sign)),
+                keyword => Err(format!("unknown constraint '{}'", keyword)),
+            };
+            result.map_err(|e| format!("line {}: {}", n+1, e))?;
+        }
+        returned.check_cages(shape)?;
+        returned.check_dots()?;
+        returned.check_parities()?;
+        Ok(returned)
+    }
+
+    /// Reads a constraint file
+    pub fn from_file(path:&Path, shape:&Shape) -> Result<Constraints,String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("error reading {}: {}", path.display(), e))?;
+        Constraints::parse(&text, shape)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cages.is_empty() && self.dots.is_empty() && !self.kropki_negative && !self.nonconsecutive &&
+            self.thermos.is_empty() && self.arrows.is_empty() && self.lines.is_empty() &&
+            self.sandwiches.is_empty() && self.little_killers.is_empty() && self.parities.is_empty() && self.signs.is_empty() &&
+            self.custom.is_empty()
+    }
+
+    /// Cages can't overlap
+    fn check_cages(&self, shape:&Shape) -> Result<(),String> {
+        let mut seen = vec![false; shape.cell_count()];
+        for cage in &self.cages {
+            for &(i,j) in &cage.cells {
+                if seen[i*shape.size + j] {
+                    return Err(format!("r{}c{} is in more than one cage", i+1, j+1));
+                }
+                seen[i*shape.size + j] = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Two cells can only have one dot or sign between them
+    fn check_dots(&self) -> Result<(),String> {
+        let pairs: Vec<[(usize,usize); 2]> = self.dots.iter().map(|dot| dot.cells).chain(self.signs.iter().map(|sign| sign.cells)).collect();
+        for (k,&[a,b]) in pairs.iter().enumerate() {
+            if pairs[..k].iter().any(|&other| other == [a,b] || other == [b,a]) {
+                return Err(format!("r{}c{} and r{}c{} have more than one dot or sign", a.0+1, a.1+1, b.0+1, b.1+1));
+            }
+        }
+        Ok(())
+    }
+
+    /// A cell can't be shaded twice
+    fn check_parities(&self) -> Result<(),String> {
+        for (k,&((i,j),_)) in self.parities.iter().enumerate() {
+            if self.parities[..k].iter().any(|&(other,_)| other == (i,j)) {
+                return Err(format!("r{}c{} is shaded more than once", i+1, j+1));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a cell written as r<row>c<col>, e.g. r1c2 for the second cell of the first row
+pub fn parse_cell(text:&str, shape:&Shape) -> Result<(usize,usize),String> {
+    let lower = text.to_ascii_lowercase();
+    let cell = lower.strip_prefix('r')
+        .and_then(|rest| rest.split_once('c'))
+        .and_then(|(r,c)| Some((r.parse::<usize>().ok()?, c.parse::<usize>().ok()?)));
+    match cell {
+        Some((r,c)) if (1..=shape.size).contains(&r) && (1..=shape.size).contains(&c) => Ok((r-1,c-1)),
+        _ => Err(format!("'{}' is not a cell on the board", text)),
+    }
+}
+
+/// Reads a list of cells, none of which may be repeated
+pub fn parse_cells(words:&[&str], shape:&Shape) -> Result<Vec<(usize,usize)>,String> {
+    let mut returned: Vec<(usize,usize)> = Vec::new();
+    for word in words {
+        let cell = parse_cell(word, shape)?;
+        if returned.contains(&cell) {
+            return Err(format!("{} is listed twice", word));
+        }
+        returned.push(cell);
+    }
+    Ok(returned)
+}
+
+fn parse_cage(words:&[&str], shape:&Shape) -> Result<Cage,String> {
+    let (sum, cells) = words.split_first().ok_or("a cage needs a sum and its cells")?;
+    let sum = sum.parse::<usize>().map_err(|_| format!("'{}' is not a cage sum", sum))?;
+    Cage::new(parse_cells(cells, shape)?, sum, shape)
+}
+
+fn parse_dot(words:&[&str], kind:DotKind, shape:&Shape) -> Result<Dot,String> {
+    match parse_cells(words, shape)?[..] {
+        [a,b] => Dot::new([a,b], kind),
+        _ => Err("a dot goes between exactly two cells".to_string()),
+    }
+}
+
+fn parse_sign(words:&[&str], shape:&Shape) -> Result<Sign,String> {
+    match parse_cells(words, shape)?[..] {
+        [bigger,smaller] => Sign::new(bigger, smaller),
+        _ => Err("a sign goes between exactly two cells, the bigger one first".to_string()),
+    }
+}
+
+fn parse_arrow(words:&[&str], shape:&Shape) -> Result<Arrow,String> {
+    let cells = parse_cells(words, shape)?;
+    let (&circle, rest) = cells.split_first().ok_or("an arrow needs a circle and at least one cell")?;
+    Arrow::new(circle, rest.to_vec())
+}
+
+fn parse_line(words:&[&str], kind:LineKind, shape:&Shape) -> Result<Line,String> {
+    Line::new(kind, parse_cells(words, shape)?, shape.size)
+}
+
+/// Reads a sandwich written as row or col, the one based index, and the sum, e.g. row 3 15
+fn parse_sandwich(words:&[&str], shape:&Shape) -> Result<Sandwich,String> {
+    let [line, index, sum] = words else {
+        return Err("a sandwich needs row or col, its number, and the sum".to_string());
+    };
+    let index = index.parse::<usize>().ok().filter(|i| (1..=shape.size).contains(i)).ok_or(format!("'{}' is not a row or column", index))?;
+    let unit = match *line {
+        "row" => Unit::Row(index-1),
+        "col" => Unit::Col(index-1),
+        _ => return Err(format!("a sandwich goes on a row or col, not '{}'", line)),
+    };
+    let sum = sum.parse::<usize>().map_err(|_| format!("'{}' is not a sandwich sum", sum))?;
+    Sandwich::new(unit, sum, shape)
+}
+
+/// Reads a little killer written as its sum, the first cell of the diagonal, and the direction it runs in, e.g. 23 r1c2 dr
+fn parse_little_killer(words:&[&str], shape:&Shape) -> Result<LittleKiller,String> {
+    let [sum, start, direction] = words else {
+        return Err("a little killer needs a sum, its first cell, and a direction".to_string());
+    };
+    let sum = sum.parse::<usize>().map_err(|_| format!("'{}' is not a little killer sum", sum))?;
+    LittleKiller::new(parse_cell(start, shape)?, Direction::parse(direction)?, sum, shape)
+}
+
+/// Rules that apply to the whole board don't take any arguments
+fn parse_flag(words:&[&str]) -> Result<(),String> {
+    if words.is_empty() {Ok(())} else {Err(format!("unexpected '{}'", words[0]))}
+}
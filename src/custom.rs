@@ -0,0 +1,155 @@
+//-----------------------------------------------------------------------------
+// Custom Constraints
+//   House rules written outside this crate. A rule implements Constraint and
+//   is registered on a board with add_constraint, after which solve_loop,
+//   hint, the human style solver, and the solution counter all apply it
+//   along with the built in variants. Rules read the board with
+//   GameBoard::options and remove possible values with GameBoard::restrict.
+//   Anything else written to a cell has to go through GameBoard::cell_mut so
+//   the search can undo it
+//-----------------------------------------------------------------------------
+
+use std::fmt;
+use std::rc::Rc;
+use crate::GameBoard;
+use crate::steps::{cell_list, digit_list, Step, Technique};
+
+/// True if a rule removed any possible values
+pub type Changed = bool;
+
+/// A rule that can no longer be met, with the reason why
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Contradiction(pub String);
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,"{}",self.0)
+    }
+}
+
+/// A rule the solver doesn't know about. Boards share their rules with every copy made while searching, so
+/// propagate should only change the board it is given
+pub trait Constraint: fmt::Debug {
+    /// Removes the possible values that would break the rule. Returns whether anything was removed, or a
+    /// Contradiction if the placed values already break it or no values are left that could meet it
+    fn propagate(&self, board:&mut GameBoard) -> Result<Changed,Contradiction>;
+
+    /// Describes the rule for the step log, e.g. "the four corners hold different values"
+    fn explain(&self) -> Option<String> {
+        None
+    }
+}
+
+impl GameBoard {
+    /// Registers a custom rule. Call after set_rules, which replaces every constraint when given a constraint file
+    pub fn add_constraint(&mut self, constraint:impl Constraint + 'static) {
+        Rc::make_mut(&mut self.constraints).custom.push(Rc::new(constraint));
+    }
+
+    /// applies every custom rule once, stopping at the first one that reports a contradiction. Boolean return value
+    /// indicates a rule is broken
+    pub fn set_possible_values_by_custom(&mut self) -> bool {
+        self.custom_conflict = None;
+        for k in 0..self.constraints.custom.len() {
+            self.apply_custom_constraint(k);
+            if self.custom_conflict.is_some() {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// applies one custom rule and logs the possible values it removed. A contradiction is kept for is_unsolvable.
+    /// Boolean return value indicates changes were made or the rule is broken
+    pub fn apply_custom_constraint(&mut self, k:usize) -> bool {
+        let constraint = Rc::clone(&self.constraints.custom[k]);
+        let before: Vec<Vec<usize>> = self.shape.cells().map(|c| self.options(c)).collect();
+        match constraint.propagate(self) {
+            Ok(true) => {}
+            Ok(false) => return false,
+            Err(contradiction) => {
+                self.custom_conflict = Some(contradiction);
+                return true;
+            }
+        }
+        // trust the board over the rule's answer so a rule that claims changes it didn't make can't stall the solver
+        let mut changed = Vec::new();
+        let mut removed = Vec::new();
+        for (cell, before) in self.shape.cells().zip(before) {
+            let after = self.options(cell);
+            let lost: Vec<usize> = before.into_iter().filter(|v| !after.contains(v)).collect();
+            if !lost.is_empty() {
+                changed.push(cell);
+                removed.extend(lost);
+            }
+        }
+        if changed.is_empty() {
+            return false;
+        }
+        removed.sort();
+        removed.dedup();
+        if self.record_steps {
+            let rule = constraint.explain().unwrap_or_else(|| "a custom rule".to_string());
+            let reason = format!("{}, so {} are removed from {}", rule, digit_list(&removed), cell_list(&changed));
+            self.steps.push(Step::new(Technique::Custom, changed, removed, reason));
+        }
+        true
+    }
+
+    /// check to see if a custom rule reported a contradiction in the last propagation pass. The values placed at the
+    /// end of that pass haven't been seen by the rules, so a solved board is checked again on a copy
+    pub fn has_custom_conflicts(&self) -> bool {
+        if self.custom_conflict.is_some() {
+            return true;
+        }
+        if self.constraints.custom.is_empty() || !self.is_solved() {
+            return false;
+        }
+        let mut board = self.search_clone();
+        self.constraints.custom.iter().any(|constraint| constraint.propagate(&mut board).is_err())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// breaks when r1c1 holds 4 but never removes anything, so only the contradiction can rule it out
+    #[derive(Debug)]
+    struct NotFourInCorner;
+    impl Constraint for NotFourInCorner {
+        fn propagate(&self, board:&mut GameBoard) -> Result<Changed,Contradiction> {
+            if board.board[0][0].borrow().value == 4 {
+                return Err(Contradiction("r1c1 is 4".to_string()));
+            }
+            Ok(false)
+        }
+    }
+
+    const PUZZLE: &str = "002000063009000001006000400020180070900760000070490816000800007300040008008000940";
+    const SOLUTION: &str = "412958763739624581856371492624185379981763254573492816245819637397546128168237945";
+
+    #[test]
+    fn contradiction_stops_propagation() {
+        let mut board = GameBoard::new();
+        board.init_board_from_string(SOLUTION.replacen('4', "0", 1));
+        board.add_constraint(NotFourInCorner);
+        assert!(!board.has_custom_conflicts());
+        // the 4 is placed after the rules run, so only the check of the solved board sees it
+        assert!(board.propagate());
+        assert!(board.custom_conflict.is_none());
+        assert!(board.has_custom_conflicts());
+        // the next pass stops at the rule
+        assert!(!board.propagate());
+        assert!(board.custom_conflict.is_some());
+    }
+
+    #[test]
+    fn contradiction_rules_out_the_only_solution() {
+        let mut board = GameBoard::new();
+        board.init_board_from_string(PUZZLE.to_string());
+        assert_eq!(board.count_solutions(2), 1);
+        board.add_constraint(NotFourInCorner);
+        assert_eq!(board.count_solutions(2), 0);
+        assert!(!board.solve_loop(false));
+    }
+}
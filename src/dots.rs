@@ -0,0 +1,163 @@
+//-----------------------------------------------------------------------------
+// Kropki Dots and XV
+//   A white dot between two neighbouring cells means their values are one
+//   apart, and a black dot means one value is double the other. With the
+//   negative constraint neighbours without a dot can be neither, and in a
+//   non-consecutive puzzle no neighbours without a white dot are one apart.
+//   An X or V between two cells works the same way, the values adding up to
+//   10 or 5
+//-----------------------------------------------------------------------------
+
+use crate::GameBoard;
+use crate::steps::{cell_list, cell_name, digit_list, Step, Technique};
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum DotKind {
+    White, // the values are one apart
+    Black, // one value is double the other
+    X, // the values add up to 10
+    V, // the values add up to 5
+}
+
+#[derive(Clone,Debug)]
+pub struct Dot {
+    pub cells: [(usize,usize); 2],
+    pub kind: DotKind,
+}
+impl Dot {
+    pub fn new(cells:[(usize,usize); 2], kind:DotKind) -> Result<Dot,String> {
+        let [a,b] = cells;
+        if a.0.abs_diff(b.0) + a.1.abs_diff(b.1) != 1 {
+            return Err(format!("{} and {} are not next to each other", cell_name(a), cell_name(b)));
+        }
+        Ok(Dot{cells,kind})
+    }
+
+    /// The character drawn between the two cells
+    pub fn symbol(&self) -> char {
+        match self.kind {
+            DotKind::White => 'o',
+            DotKind::Black => '*',
+            DotKind::X => 'X',
+            DotKind::V => 'V',
+        }
+    }
+
+    /// True if the dot sits between the two cells, in either order
+    pub fn joins(&self, a:(usize,usize), b:(usize,usize)) -> bool {
+        self.cells == [a,b] || self.cells == [b,a]
+    }
+}
+
+/// What the values of two neighbouring cells must or mustn't be
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum PairRule {
+    Consecutive, // a white dot
+    Double, // a black dot
+    Sum(usize), // an X or V
+    NotConsecutive, // no white dot in a non-consecutive puzzle
+    Neither, // no dot at all with the negative constraint
+}
+impl PairRule {
+    pub fn allows(&self, x:usize, y:usize) -> bool {
+        let consecutive = x.abs_diff(y) == 1;
+        let double = x == 2*y || y == 2*x;
+        match self {
+            PairRule::Consecutive => consecutive,
+            PairRule::Double => double,
+            PairRule::Sum(sum) => x + y == *sum,
+            PairRule::NotConsecutive => !consecutive,
+            PairRule::Neither => !consecutive && !double,
+        }
+    }
+
+    fn technique(&self) -> Technique {
+        if matches!(self, PairRule::Sum(_)) {Technique::XV} else {Technique::Kropki}
+    }
+
+    fn describe(&self, a:(usize,usize), b:(usize,usize)) -> String {
+        let (a,b) = (cell_name(a), cell_name(b));
+        match self {
+            PairRule::Consecutive => format!("the white dot between {} and {} makes them one apart", a, b),
+            PairRule::Double => format!("the black dot between {} and {} makes one double the other", a, b),
+            PairRule::Sum(10) => format!("the X between {} and {} makes them add up to 10", a, b),
+            PairRule::Sum(sum) => format!("the V between {} and {} makes them add up to {}", a, b, sum),
+            PairRule::NotConsecutive => format!("without a white dot {} and {} can't be one apart", a, b),
+            PairRule::Neither => format!("without a dot {} and {} can't be one apart or double", a, b),
+        }
+    }
+}
+
+impl GameBoard {
+    /// every rule between neighbouring cells: one for each dot, plus the negative and non-consecutive rules for the
+    /// neighbours they cover. A black dot in a non-consecutive puzzle gets both rules
+    pub fn pair_rules(&self) -> Vec<([(usize,usize); 2], PairRule)> {
+        let constraints = &self.constraints;
+        let mut returned: Vec<([(usize,usize); 2], PairRule)> = constraints.dots.iter().map(|dot| match dot.kind {
+            DotKind::White => (dot.cells, PairRule::Consecutive),
+            DotKind::Black => (dot.cells, PairRule::Double),
+            DotKind::X => (dot.cells, PairRule::Sum(10)),
+            DotKind::V => (dot.cells, PairRule::Sum(5)),
+        }).collect();
+        if !constraints.nonconsecutive && !constraints.kropki_negative {
+            return returned;
+        }
+        let size = self.shape.size;
+        for (i,j) in self.shape.cells() {
+            for b in [(i,j+1),(i+1,j)].into_iter().filter(|&(a,b)| a < size && b < size) {
+                let dot = constraints.dots.iter().find(|dot| dot.joins((i,j), b)).map(|dot| dot.kind);
+                let rule = match dot {
+                    None if constraints.kropki_negative => PairRule::Neither,
+                    None | Some(DotKind::Black | DotKind::X | DotKind::V) if constraints.nonconsecutive => PairRule::NotConsecutive,
+                    _ => continue,
+                };
+                returned.push(([(i,j),b], rule));
+            }
+        }
+        returned
+    }
+
+    /// keeps only the possible values of neighbouring cells that fit the dot between them, or the lack of one
+    pub fn set_possible_values_by_dots(&mut self) {
+        for (cells, rule) in self.pair_rules() {
+            self.apply_pair_rule(cells, rule);
+        }
+    }
+
+    /// applies the first Kropki or XV rule, depending on the technique, that makes progress. Boolean return value indicates changes were made
+    pub fn apply_first_dot(&mut self, technique:Technique) -> bool {
+        self.pair_rules().into_iter().filter(|(_, rule)| rule.technique() == technique).any(|(cells, rule)| self.apply_pair_rule(cells, rule))
+    }
+
+    /// removes the possible values of each cell that no value of the other cell fits with
+    fn apply_pair_rule(&mut self, cells:[(usize,usize); 2], rule:PairRule) -> bool {
+        let options: Vec<Vec<usize>> = cells.iter().map(|&c| self.options(c)).collect();
+        let open: Vec<(usize,usize)> = cells.iter().copied().filter(|&(i,j)| self.board[i][j].borrow().value == 0).collect();
+        let allowed: Vec<Vec<usize>> = open.iter().map(|&c| {
+            let (this, other) = if c == cells[0] {(&options[0], &options[1])} else {(&options[1], &options[0])};
+            this.iter().copied().filter(|&x| other.iter().any(|&y| rule.allows(x, y))).collect()
+        }).collect();
+        let (changed, removed) = self.restrict(&open, &allowed);
+        if changed.is_empty() {
+            return false;
+        }
+        if self.record_steps {
+            let reason = format!("{}, so {} are removed from {}", rule.describe(cells[0], cells[1]), digit_list(&removed), cell_list(&changed));
+            self.steps.push(Step::new(rule.technique(), changed, removed, reason));
+        }
+        true
+    }
+
+    /// the dot, X, or V drawn between two cells, if there is one
+    pub fn dot_between(&self, a:(usize,usize), b:(usize,usize)) -> Option<char> {
+        self.constraints.dots.iter().find(|dot| dot.joins(a, b)).map(|dot| dot.symbol())
+    }
+
+    /// check to see if any neighbouring values break the dot, X, or V between them, or the lack of one
+    pub fn has_dot_conflicts(&self) -> bool {
+        self.pair_rules().iter().any(|&([a,b], rule)| {
+            let (x, y) = (self.board[a.0][a.1].borrow().value, self.board[b.0][b.1].borrow().value);
+            x != 0 && y != 0 && !rule.allows(x, y)
+        })
+    }
+}
@@ -0,0 +1,238 @@
+//-----------------------------------------------------------------------------
+// Puzzle Generator
+//   Fills an empty board by randomised search, then removes clues in a
+//   random order as long as the puzzle keeps exactly one solution. The
+//   solution is known, so after each removal it only looks for a different
+//   one rather than counting solutions from scratch, and gives up on clues
+//   whose removal takes too long to check by keeping them
+//-----------------------------------------------------------------------------
+
+use std::time::{Duration, Instant};
+use crate::GameBoard;
+use crate::random::Rng;
+use crate::search::{Budget, SolveOptions};
+use crate::rating::{Rating, Tier};
+use crate::shape::Shape;
+use crate::steps::Technique;
+
+/// The number of solution grids tried before giving up on a clue mask
+const MASK_ATTEMPTS: usize = 10000;
+
+/// The most guesses a search checking whether a clue can be removed may make before the clue is kept. Classic puzzles
+/// never get close, but on 16x16 and larger boards a few checks would otherwise take minutes each
+const MAX_CHECK_GUESSES: usize = 100;
+
+/// Symmetry of the clue layout. Clues are removed a whole group of symmetric cells at a time
+#[derive(Clone,Copy,Debug,PartialEq,Eq,clap::ValueEnum)]
+pub enum Symmetry {
+    None,
+    Rot180, // half turn
+    Rot90, // quarter turn
+    MirrorH, // mirrored top to bottom
+    MirrorV, // mirrored left to right
+    Diagonal, // mirrored along the main diagonal
+    Full, // every rotation and mirror
+}
+impl Symmetry {
+    /// the cells a cell is mapped to by each rotation or mirror of the symmetry, including itself
+    pub fn orbit(&self, cell:(usize,usize), shape:&Shape) -> Vec<(usize,usize)> {
+        let (i,j) = cell;
+        let n = shape.size-1; // the last row and column
+        let mut returned = match self {
+            Symmetry::None => vec![(i,j)],
+            Symmetry::Rot180 => vec![(i,j),(n-i,n-j)],
+            Symmetry::Rot90 => vec![(i,j),(j,n-i),(n-i,n-j),(n-j,i)],
+            Symmetry::MirrorH => vec![(i,j),(n-i,j)],
+            Symmetry::MirrorV => vec![(i,j),(i,n-j)],
+            Symmetry::Diagonal => vec![(i,j),(j,i)],
+            Symmetry::Full => vec![(i,j),(j,n-i),(n-i,n-j),(n-j,i),(n-i,j),(i,n-j),(j,i),(n-j,n-i)],
+        };
+        returned.sort();
+        returned.dedup();
+        returned
+    }
+
+    /// splits the board into groups of cells that map onto each other
+    pub fn groups(&self, shape:&Shape) -> Vec<Vec<(usize,usize)>> {
+        let mut returned: Vec<Vec<(usize,usize)>> = Vec::new();
+        for cell in shape.cells() {
+            let orbit = self.orbit(cell, shape);
+            if orbit[0] == cell { // only keep each group once, from its first cell
+                returned.push(orbit);
+            }
+        }
+        returned
+    }
+}
+
+/// reads a clue mask with one character per cell where 'x' marks a clue and '.' an empty cell
+pub fn parse_mask(mask:&str, shape:&Shape) -> Vec<bool> {
+    let returned: Vec<bool> = mask.chars().filter(|c| !c.is_whitespace()).map(|c| match c {
+        'x' | 'X' => true,
+        '.' => false,
+        _ => panic!("Invalid character found in mask: {}", c),
+    }).collect();
+    if returned.len() != shape.cell_count() {
+        panic!("Mask must be exactly {} characters long", shape.cell_count());
+    }
+    returned
+}
+
+impl GameBoard {
+    /// fills the board with a random complete solution. Returns false if the board can't be completed
+    pub fn fill_random(&mut self, rng:&mut Rng) -> bool {
+        let mut board = self.search_clone();
+        if board.search(1, |choices| rng.shuffle(choices), &mut Budget::unlimited()) == 0 {return false;}
+        self.board = board.board;
+        true
+    }
+
+    /// removes groups of symmetric clues in a random order, putting back any group whose removal allows a second solution
+    pub fn remove_clues(&mut self, rng:&mut Rng, symmetry:Symmetry) {
+        let mut groups = symmetry.groups(&self.shape);
+        rng.shuffle(&mut groups);
+        for group in groups {
+            let values: Vec<usize> = group.iter().map(|&(i,j)| self.board[i][j].borrow().value).collect();
+            if values.iter().all(|&v| v == 0) {continue;}
+            for &(i,j) in &group {
+                self.board[i][j].get_mut().set_value(0);
+            }
+            let removed: Vec<((usize,usize),usize)> = group.iter().copied().zip(values.iter().copied()).filter(|&(_,v)| v != 0).collect();
+            if self.has_other_solution(&removed) {
+                for (&(i,j),&value) in group.iter().zip(&values) {
+                    self.board[i][j].get_mut().set_value(value);
+                }
+            }
+        }
+    }
+
+    /// true if clearing the clues in `removed` let the board have a solution other than the one it had, or a check
+    /// ran out of guesses. Any other solution differs from it in a cleared cell, so each cleared cell gets one search
+    /// with its old value ruled out and the cleared cells before it put back. The old solution is never searched
+    fn has_other_solution(&self, removed:&[((usize,usize),usize)]) -> bool {
+        (0..removed.len()).any(|k| {
+            let mut budget = Budget::new(SolveOptions{max_nodes:Some(MAX_CHECK_GUESSES), ..SolveOptions::default()});
+            let mut board = self.search_clone();
+            for &((i,j),value) in &removed[..k] {
+                board.board[i][j].get_mut().set_value(value);
+            }
+            let (cell,value) = removed[k];
+            board.remove_candidate(cell, value);
+            board.search(1, |_| {}, &mut budget) > 0 || budget.stopped.is_some()
+        })
+    }
+
+    /// clears every cell not marked as a clue in the mask
+    pub fn apply_mask(&mut self, mask:&[bool]) {
+        for ((i,j),&keep) in self.shape.cells().zip(mask) {
+            if !keep {
+                self.board[i][j].get_mut().set_value(0);
+            }
+        }
+    }
+}
+
+/// generates a random puzzle with exactly one solution and clues laid out with the given symmetry.
+/// Returns None if the shape's rules leave no way to fill the board
+pub fn generate(rng:&mut Rng, shape:&Shape, symmetry:Symmetry) -> Option<GameBoard> {
+    let mut board = GameBoard::with_shape(shape.clone());
+    if !board.fill_random(rng) {
+        return None;
+    }
+    board.remove_clues(rng, symmetry);
+    board.set_initial_cells();
+    Some(board)
+}
+
+/// generates a random puzzle with exactly one solution whose clues are exactly the cells marked in the mask.
+/// Returns None if no such puzzle was found within MASK_ATTEMPTS solution grids, or the board can't be filled at all
+pub fn generate_from_mask(rng:&mut Rng, shape:&Shape, mask:&[bool]) -> Option<GameBoard> {
+    for _ in 0..MASK_ATTEMPTS {
+        let mut board = GameBoard::with_shape(shape.clone());
+        if !board.fill_random(rng) {
+            return None;
+        }
+        board.apply_mask(mask);
+        if board.has_unique_solution() {
+            board.set_initial_cells();
+            return Some(board);
+        }
+    }
+    None
+}
+
+//-------------------------------------
+// Targeted Generation
+//   Keeps generating puzzles until one is rated in the requested tier or
+//   needs the requested technique, or the time budget runs out
+//-------------------------------------
+
+/// What a targeted search is looking for. Every field that is set must match
+#[derive(Clone,Copy,Debug,Default)]
+pub struct Target {
+    pub tier: Option<Tier>,
+    pub requires: Option<Technique>,
+}
+impl Target {
+    pub fn is_set(&self) -> bool {
+        self.tier.is_some() || self.requires.is_some()
+    }
+
+    /// checks a rating against the target
+    pub fn matches(&self, rating:&Rating) -> bool {
+        self.tier.is_none_or(|tier| rating.tier == tier) &&
+            self.requires.is_none_or(|technique| rating.histogram.contains_key(&technique))
+    }
+}
+
+/// How much work a targeted search did
+#[derive(Clone,Copy,Debug)]
+pub struct SearchReport {
+    pub candidates: usize,
+    pub elapsed: Duration,
+}
+
+/// generates and rates puzzles until one matches the target or the time budget runs out.
+/// Candidates come from the mask when one is given, otherwise from the symmetry
+pub fn generate_targeted(rng:&mut Rng, shape:&Shape, symmetry:Symmetry, mask:Option<&[bool]>, target:Target, budget:Duration) -> (Option<GameBoard>, SearchReport) {
+    let start = Instant::now();
+    let mut candidates = 0;
+    while start.elapsed() < budget {
+        let puzzle = match mask {
+            Some(mask) => match generate_from_mask(rng, shape, mask) {
+                Some(puzzle) => puzzle,
+                None => break,
+            },
+            None => match generate(rng, shape, symmetry) {
+                Some(puzzle) => puzzle,
+                None => break,
+            },
+        };
+        candidates += 1;
+        if target.matches(&puzzle.rate()) {
+            return (Some(puzzle), SearchReport{candidates, elapsed:start.elapsed()});
+        }
+    }
+    (None, SearchReport{candidates, elapsed:start.elapsed()})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Variant;
+
+    #[test]
+    fn seeded_puzzles_are_unique() {
+        for seed in 0..5 {
+            let puzzle = generate(&mut Rng::new(seed), &Shape::classic(), Symmetry::Rot180).unwrap();
+            assert!(puzzle.has_unique_solution(), "seed {} gave a puzzle without a unique solution", seed);
+        }
+    }
+
+    #[test]
+    fn seeded_variant_puzzles_are_unique() {
+        let shape = Shape::classic().with_variants(&[Variant::X]);
+        let puzzle = generate(&mut Rng::new(7), &shape, Symmetry::None).unwrap();
+        assert!(puzzle.has_unique_solution());
+    }
+}
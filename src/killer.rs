@@ -0,0 +1,367 @@
+//-----------------------------------------------------------------------------
+// Killer Cages
+//   A cage is a group of cells whose values add up to its sum without any
+//   value repeated. Cage sums are checked against a table of every set of
+//   values that makes the sum, and the 45 rule works out the sum of the
+//   cells poking in (innies) or out (outies) of a group of rows, columns,
+//   or a square, since each row, column, and square adds up to the same total
+//-----------------------------------------------------------------------------
+
+use std::rc::Rc;
+use crate::GameBoard;
+use crate::shape::{Shape, Unit};
+use crate::steps::{cell_list, cell_name, digit_list, Step, Technique};
+use crate::strategies::combinations;
+
+/// Innies and outies with more cells than this are too loose to be worth checking
+const MAX_45_CELLS: usize = 4;
+
+#[derive(Clone,Debug)]
+pub struct Cage {
+    pub cells: Vec<(usize,usize)>,
+    pub sum: usize,
+    combos: Vec<Vec<usize>>, // every set of different values that adds up to the sum, smallest values first
+}
+impl Cage {
+    pub fn new(cells:Vec<(usize,usize)>, sum:usize, shape:&Shape) -> Result<Cage,String> {
+        if cells.is_empty() {
+            return Err("a cage needs at least one cell".to_string());
+        }
+        let combos = combination_table(cells.len(), sum, shape.size);
+        if combos.is_empty() {
+            return Err(format!("no {} different values add up to {}", cells.len(), sum));
+        }
+        Ok(Cage{cells,sum,combos})
+    }
+
+    /// The top left cell of the cage, where its sum is written
+    pub fn corner(&self) -> (usize,usize) {
+        *self.cells.iter().min().unwrap()
+    }
+
+    fn name(&self) -> String {
+        format!("the {} cage at {}", self.sum, cell_name(self.corner()))
+    }
+}
+
+/// A group of whole rows, columns, or a square for the 45 rule, with the total its values add up to
+struct Group {
+    name: String,
+    cells: Vec<(usize,usize)>,
+    total: usize,
+}
+
+/// Every set of `count` different values from 1..=size that adds up to `sum`
+pub fn combination_table(count:usize, sum:usize, size:usize) -> Vec<Vec<usize>> {
+    combinations(size, count).into_iter()
+        .map(|combo| combo.into_iter().map(|k| k+1).collect::<Vec<usize>>())
+        .filter(|combo| combo.iter().sum::<usize>() == sum)
+        .collect()
+}
+
+impl GameBoard {
+    /// the index of the cage a cell is in
+    pub fn cage_of(&self, cell:(usize,usize)) -> Option<usize> {
+        self.constraints.cages.iter().position(|cage| cage.cells.contains(&cell))
+    }
+
+    /// the values placed in the cells and the cells that are still unsolved
+    fn cage_cells(&self, cells:&[(usize,usize)]) -> (Vec<usize>, Vec<(usize,usize)>) {
+        let placed = cells.iter().map(|&(i,j)| self.board[i][j].borrow().value).filter(|&v| v != 0).collect();
+        let open = cells.iter().copied().filter(|&(i,j)| self.board[i][j].borrow().value == 0).collect();
+        (placed, open)
+    }
+
+    /// removes any cell possible values if that value is already placed in its cage
+    pub fn set_possible_values_by_cages(&mut self) {
+        let constraints = Rc::clone(&self.constraints);
+        for cage in &constraints.cages {
+            for &source in &cage.cells {
+                let value = self.board[source.0][source.1].get_mut().value;
+                if value == 0 {
+                    continue;
+                }
+                let changed: Vec<(usize,usize)> = cage.cells.iter().copied()
+                    .filter(|&cell| self.remove_candidate(cell, value))
+                    .collect();
+                if !changed.is_empty() && self.record_steps {
+                    let reason = format!("{} is placed at {} so it is removed from {} in {}", value, cell_name(source), cell_list(&changed), cage.name());
+                    self.steps.push(Step::new(Technique::Elimination, changed, vec![value], reason));
+                }
+            }
+        }
+    }
+
+    /// keeps only the possible values that fit one of the value sets that make each cage's sum
+    pub fn set_possible_values_by_cage_sums(&mut self) {
+        for k in 0..self.constraints.cages.len() {
+            self.filter_cage_sum(k);
+        }
+    }
+
+    /// keeps only the possible values of a cage that fit one of the value sets making its sum. Boolean return value indicates changes were made
+    pub fn filter_cage_sum(&mut self, k:usize) -> bool {
+        let constraints = Rc::clone(&self.constraints);
+        let cage = &constraints.cages[k];
+        let (placed, open) = self.cage_cells(&cage.cells);
+        if open.is_empty() {
+            return false;
+        }
+        let candidates: Vec<Vec<usize>> = open.iter().map(|&(i,j)| self.board[i][j].borrow().possible_values.clone()).collect();
+        let mut allowed: Vec<Vec<usize>> = vec![Vec::new(); open.len()];
+        let mut fitting: Vec<&Vec<usize>> = Vec::new();
+        for combo in &cage.combos {
+            if !placed.iter().all(|v| combo.contains(v)) {continue;}
+            let rest: Vec<usize> = combo.iter().copied().filter(|v| !placed.contains(v)).collect();
+            if rest.len() != open.len() {continue;}
+            let options: Vec<Vec<usize>> = candidates.iter().map(|c| c.iter().copied().filter(|v| rest.contains(v)).collect()).collect();
+            if !has_matching(&options) {continue;}
+            fitting.push(combo);
+            for (n, cell_options) in options.iter().enumerate() {
+                for &value in cell_options {
+                    if allowed[n].contains(&value) {continue;}
+                    // the value fits if the other cells can still take the rest of the set
+                    let others: Vec<Vec<usize>> = options.iter().enumerate()
+                        .filter(|&(m,_)| m != n)
+                        .map(|(_,o)| o.iter().copied().filter(|&v| v != value).collect())
+                        .collect();
+                    if has_matching(&others) {
+                        allowed[n].push(value);
+                    }
+                }
+            }
+        }
+        let (changed, removed) = self.restrict(&open, &allowed);
+        if changed.is_empty() {
+            return false;
+        }
+        if self.record_steps {
+            let sets: Vec<String> = fitting.iter().map(|combo| combo.iter().map(|v| v.to_string()).collect::<Vec<String>>().join("+")).collect();
+            let made_from = if sets.is_empty() {"can't be made from its possible values".to_string()} else {format!("can only be made from {}", sets.join(" or "))};
+            let reason = format!("{} {} so {} are removed from {}", cage.name(), made_from, digit_list(&removed), cell_list(&changed));
+            self.steps.push(Step::new(Technique::CageSum, changed, removed, reason));
+        }
+        true
+    }
+
+    /// removes every possible value of each cell that isn't in its list of allowed values. Returns the cells that changed and the values removed
+    pub fn restrict(&mut self, cells:&[(usize,usize)], allowed:&[Vec<usize>]) -> (Vec<(usize,usize)>, Vec<usize>) {
+        let mut changed = Vec::new();
+        let mut removed = Vec::new();
+        for (&(i,j), allowed) in cells.iter().zip(allowed) {
+            let before = self.board[i][j].get_mut().possible_values.clone();
+            for value in before.into_iter().filter(|v| !allowed.contains(v)) {
+                self.remove_candidate((i,j), value);
+                if !removed.contains(&value) {removed.push(value);}
+                if !changed.contains(&(i,j)) {changed.push((i,j));}
+            }
+        }
+        removed.sort();
+        (changed, removed)
+    }
+
+    /// the groups the 45 rule is applied to: every run of whole rows or columns short of the full board, and every square
+    fn killer_groups(&self) -> Vec<Group> {
+        let size = self.shape.size;
+        let unit_total = size*(size+1)/2;
+        let mut returned = Vec::new();
+        for len in 1..size {
+            for start in 0..=size-len {
+                let (rows_name, cols_name) = if len == 1 {
+                    (Unit::Row(start).to_string(), Unit::Col(start).to_string())
+                } else {
+                    (format!("rows {}-{}", start+1, start+len), format!("columns {}-{}", start+1, start+len))
+                };
+                let rows = (start..start+len).flat_map(|i| (0..size).map(move |j| (i,j))).collect();
+                returned.push(Group{name:rows_name, cells:rows, total:unit_total*len});
+                let cols = (start..start+len).flat_map(|j| (0..size).map(move |i| (i,j))).collect();
+                returned.push(Group{name:cols_name, cells:cols, total:unit_total*len});
+            }
+        }
+        for sq in 0..size {
+            returned.push(Group{name:Unit::Square(sq).to_string(), cells:self.shape.unit_cells(Unit::Square(sq)), total:unit_total});
+        }
+        returned
+    }
+
+    /// applies the 45 rule to every group
+    pub fn set_possible_values_by_45_rule(&mut self) {
+        if self.constraints.cages.is_empty() {
+            return;
+        }
+        for group in self.killer_groups() {
+            self.apply_45_rule(&group);
+        }
+    }
+
+    /// applies the 45 rule to the first group it makes progress in. Boolean return value indicates changes were made
+    pub fn apply_first_45_rule(&mut self) -> bool {
+        if self.constraints.cages.is_empty() {
+            return false;
+        }
+        self.killer_groups().iter().any(|group| self.apply_45_rule(group))
+    }
+
+    /// the cells of a group not covered by the cages inside it (innies) add up to the group's total less those cages,
+    /// and when cages cover the whole group the cells they poke out with (outies) add up to the cages less the total
+    fn apply_45_rule(&mut self, group:&Group) -> bool {
+        let (name, total) = (&group.name, group.total);
+        let group = &group.cells;
+        let constraints = Rc::clone(&self.constraints);
+        let touching: Vec<&Cage> = constraints.cages.iter().filter(|cage| cage.cells.iter().any(|c| group.contains(c))).collect();
+        let (inside, poking): (Vec<&Cage>, Vec<&Cage>) = touching.iter().partition(|cage| cage.cells.iter().all(|c| group.contains(c)));
+
+        let innies: Vec<(usize,usize)> = group.iter().copied().filter(|c| !inside.iter().any(|cage| cage.cells.contains(c))).collect();
+        let inside_sum: usize = inside.iter().map(|cage| cage.sum).sum();
+        if !innies.is_empty() && innies.len() <= MAX_45_CELLS {
+            if let Some(sum) = total.checked_sub(inside_sum) {
+                if self.restrict_sum(&innies, sum, &format!("{} adds up to {} so the innies", name, total)) {
+                    return true;
+                }
+            }
+        }
+
+        let covered = group.iter().all(|c| touching.iter().any(|cage| cage.cells.contains(c)));
+        let outies: Vec<(usize,usize)> = poking.iter().flat_map(|cage| cage.cells.iter().copied()).filter(|c| !group.contains(c)).collect();
+        let touching_sum: usize = touching.iter().map(|cage| cage.sum).sum();
+        if covered && !outies.is_empty() && outies.len() <= MAX_45_CELLS {
+            if let Some(sum) = touching_sum.checked_sub(total) {
+                if self.restrict_sum(&outies, sum, &format!("{} adds up to {} so the outies", name, total)) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// keeps only the possible values that let the cells add up to the sum. Values may repeat unless every cell sees every other
+    fn restrict_sum(&mut self, cells:&[(usize,usize)], sum:usize, description:&str) -> bool {
+        let choices: Vec<Vec<usize>> = cells.iter().map(|&c| self.options(c)).collect();
+        let distinct = cells.iter().all(|&a| cells.iter().all(|&b| a == b || self.shape.sees(a, b)));
+        let allowed = sum_options(&choices, sum, distinct);
+        let (changed, removed) = self.restrict(cells, &allowed);
+        if changed.is_empty() {
+            return false;
+        }
+        if self.record_steps {
+            let reason = format!("{} {} add up to {}, which removes {} from {}", description, cell_list(cells), sum, digit_list(&removed), cell_list(&changed));
+            self.steps.push(Step::new(Technique::InnieOutie, changed, removed, reason));
+        }
+        true
+    }
+
+    /// check to see if any cage repeats a value or can no longer make its sum
+    pub fn has_cage_conflicts(&self) -> bool {
+        self.constraints.cages.iter().any(|cage| {
+            let (placed, open) = self.cage_cells(&cage.cells);
+            let mut unique = placed.clone();
+            unique.sort();
+            unique.dedup();
+            let total: usize = placed.iter().sum();
+            unique.len() != placed.len() || total > cage.sum || (open.is_empty() && total != cage.sum)
+        })
+    }
+}
+
+/// true if every cell can be given a different value from its options
+pub fn has_matching(options:&[Vec<usize>]) -> bool {
+    // Kuhn's augmenting path algorithm, owner[v] is the cell currently given value v
+    fn assign(cell:usize, options:&[Vec<usize>], visited:&mut Vec<usize>, owner:&mut Vec<Option<usize>>) -> bool {
+        for &value in &options[cell] {
+            if visited.contains(&value) {continue;}
+            visited.push(value);
+            if owner[value].is_none_or(|other| assign(other, options, visited, owner)) {
+                owner[value] = Some(cell);
+                return true;
+            }
+        }
+        false
+    }
+    let largest = options.iter().flatten().copied().max().unwrap_or(0);
+    let mut owner = vec![None; largest+1];
+    (0..options.len()).all(|cell| assign(cell, options, &mut Vec::new(), &mut owner))
+}
+
+/// for each cell, the values from its choices that are part of some way for the cells to add up to the sum
+fn sum_options(choices:&[Vec<usize>], sum:usize, distinct:bool) -> Vec<Vec<usize>> {
+    fn search(k:usize, remaining:usize, choices:&[Vec<usize>], distinct:bool, current:&mut Vec<usize>, allowed:&mut Vec<Vec<usize>>) {
+        if k == choices.len() {
+            if remaining == 0 {
+                for (n,&value) in current.iter().enumerate() {
+                    if !allowed[n].contains(&value) {allowed[n].push(value);}
+                }
+            }
+            return;
+        }
+        for &value in &choices[k] {
+            if value > remaining || (distinct && current.contains(&value)) {continue;}
+            current.push(value);
+            search(k+1, remaining-value, choices, distinct, current, allowed);
+            current.pop();
+        }
+    }
+    let mut allowed = vec![Vec::new(); choices.len()];
+    search(0, sum, choices, distinct, &mut Vec::new(), &mut allowed);
+    allowed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::Constraints;
+
+    fn board(rules:&str) -> GameBoard {
+        let mut board = GameBoard::new();
+        board.constraints = Rc::new(Constraints::parse(rules, &board.shape).unwrap());
+        board
+    }
+
+    #[test]
+    fn combination_tables() {
+        assert_eq!(combination_table(2, 3, 9), vec![vec![1,2]]);
+        assert_eq!(combination_table(2, 17, 9), vec![vec![8,9]]);
+        assert_eq!(combination_table(2, 10, 9), vec![vec![1,9], vec![2,8], vec![3,7], vec![4,6]]);
+        assert_eq!(combination_table(3, 24, 9), vec![vec![7,8,9]]);
+        assert_eq!(combination_table(4, 10, 9), vec![vec![1,2,3,4]]);
+        assert_eq!(combination_table(9, 45, 9).len(), 1);
+        assert!(combination_table(2, 18, 9).is_empty());
+    }
+
+    #[test]
+    fn matching_needs_a_different_value_for_each_cell() {
+        assert!(has_matching(&[vec![1], vec![1,2], vec![2,3]]));
+        assert!(!has_matching(&[vec![1], vec![1]]));
+        assert!(!has_matching(&[vec![1,2], vec![1,2], vec![1,2]]));
+        assert!(has_matching(&[]));
+    }
+
+    #[test]
+    fn cage_sums_remove_values_that_cant_fit() {
+        let mut board = board("cage 3 r1c1 r1c2\ncage 23 r5c5 r5c6 r5c7");
+        assert!(board.filter_cage_sum(0));
+        assert_eq!(board.options((0,0)), vec![1,2]);
+        assert_eq!(board.options((0,1)), vec![1,2]);
+        assert!(board.filter_cage_sum(1));
+        assert_eq!(board.options((4,4)), vec![6,8,9]);
+        assert!(!board.filter_cage_sum(1));
+    }
+
+    #[test]
+    fn innies_of_a_row() {
+        // the cages in row 1 add up to 41, leaving 4 for r1c9
+        let mut board = board("cage 3 r1c1 r1c2\ncage 17 r1c3 r1c4\ncage 10 r1c5 r1c6\ncage 11 r1c7 r1c8");
+        board.set_possible_values_by_45_rule();
+        assert_eq!(board.options((0,8)), vec![4]);
+    }
+
+    #[test]
+    fn outies_of_a_row() {
+        // both cages cover row 1 and add up to 51, so the cell poking into row 2 is 6. The innies r1c1-r1c4 make 10,
+        // and a group stops at its innies when they change something, so the outies need a second pass
+        let mut board = board("cage 16 r1c1 r1c2 r1c3 r1c4 r2c1\ncage 35 r1c5 r1c6 r1c7 r1c8 r1c9");
+        board.set_possible_values_by_45_rule();
+        assert_eq!(board.options((0,0)), vec![1,2,3,4]);
+        board.set_possible_values_by_45_rule();
+        assert_eq!(board.options((1,0)), vec![6]);
+    }
+}
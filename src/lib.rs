@@ -0,0 +1,908 @@
+//-----------------------------------------------------------------------------
+// Sudoku Solver
+//   The board, its cells, and the solver, with the variants and tools built
+//   on them in child modules. The command line program lives in main.rs
+//-----------------------------------------------------------------------------
+
+pub mod branching;
+pub mod compare;
+pub mod cdcl;
+pub mod constraints;
+pub mod custom;
+pub mod dots;
+pub mod generator;
+pub mod killer;
+pub mod lines;
+pub mod minimal;
+pub mod multigrid;
+pub mod outside;
+pub mod random;
+pub mod rating;
+pub mod sat;
+pub mod search;
+pub mod shape;
+pub mod steps;
+pub mod strategies;
+pub mod transform;
+
+use core::panic;
+use std::{cell::RefCell, fs::File, io::Read, ops::Deref, path::PathBuf};
+use std::rc::Rc;
+use constraints::Constraints;
+use shape::{Shape, Variant};
+use search::{Budget, Trail};
+use steps::{cell_list, cell_name, digit_list, Step, Technique, Unit};
+pub use branching::Heuristic;
+pub use custom::{Changed, Constraint, Contradiction};
+pub use search::{CancelToken, SearchStats, SolveOptions, SolveResult, Status};
+
+
+
+
+//-------------------------------------
+// Game Cells
+//   (are individual board elements)
+//-------------------------------------
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum CellState {
+    Initial, // initial cells
+    Solved, //cells that have been solved in a previous itteration of the solver loop
+    New, //cells that have just been solved
+    Unsolved, //Default for unsolved cells
+    Guess, //used in recursion
+}
+#[derive(Clone,Debug)]
+pub struct GameCell {
+    pub value:usize,
+    pub possible_values:Vec<usize>,
+    pub state:CellState,
+    pub size:usize, // the largest value the cell can hold
+    pub hidden_single:bool, // its last possible value was logged as a hidden single, so it isn't logged again as a naked single
+}
+impl GameCell {
+    /// A new uninitialized cell set to a value of zero and 1..=size possible values
+    fn new(size:usize) -> GameCell {
+        GameCell{value:0,possible_values:(1..=size).collect(),state:CellState::Unsolved,size,hidden_single:false}
+    }
+
+    /// Removes a value, if present, from the possible value list. Returns true if the value was removed
+    fn remove_possible_cell_value(&mut self, v:usize) -> bool {
+        if self.value == 0 {
+            let before = self.possible_values.len();
+            self.possible_values.retain(|&x| x != v);
+            return self.possible_values.len() != before;
+        }
+        false
+    }
+
+    /// Keeps a particular possible value and removes all others. Returns true if any values were removed
+    fn keep_only_possible_cell_value(&mut self, v:usize) -> bool {
+        if self.value == 0 {
+            let before = self.possible_values.len();
+            self.possible_values.retain(|&x| x == v);
+            return self.possible_values.len() != before;
+        }
+        false
+    }
+
+    /// Sets the value of the cell and removes the list of possible values, unless setting to zero, in which case it will reset the possible values
+    fn set_value(&mut self, v:usize) {
+        self.value=v;
+        if v == 0 {
+            self.possible_values = (1..=self.size).collect();
+            self.hidden_single = false;
+        } else {
+            self.possible_values.clear();
+        }
+    }
+
+    /// Checks if there is only 1 possible value and updates the cells value accordingly.
+    fn check_possible(&mut self) -> bool {
+        if self.possible_values.len() == 1 {
+            self.set_value(self.possible_values[0]);
+            self.set_newly_solved();
+            return true;
+        }
+        false
+    }
+
+    /// Prints the value of the cell
+    fn print(&self, shape:&Shape) {print!("{}",shape.symbol(self.value));}
+
+    /// Prints the value of the cell using linux terminal escape sequences to color the output according to if the cell was an initial value of the puzzle, just solved, or previously solved
+    fn print_color(&self, shape:&Shape) {
+        let symbol = shape.symbol(self.value);
+        match self.state {
+            CellState::Initial => print!("\x1b[91m\x1b[1m{}\x1b[0m",symbol),
+            CellState::Solved => print!("{}",symbol),
+            CellState::New => print!("\x1b[97m\x1b[1m{}\x1b[0m",symbol),
+            CellState::Unsolved => print!(" "),
+            CellState::Guess => print!("\x1b[95m\x1b[1m{}\x1b[0m",symbol),
+        }
+    }
+
+    /// Prints cell in a format as wide as the board listing either the cell value or the possible cell values
+    fn print_detailed(&self, shape:&Shape) {
+        if self.value != 0 {
+            print!("{:^width$}",shape.symbol(self.value),width=shape.size);
+        } else {
+            for i in 1..=shape.size {
+                if self.possible_values.contains(&i) {
+                    print!("{}",shape.symbol(i));
+                } else {
+                    print!(" ");
+                }
+            }
+        }
+    }
+
+    /// Initializes the cell's internal state (Used for cell colors in terminal output)
+    fn set_initial(&mut self) {if self.value == 0 {self.state = CellState::Unsolved;}else{self.state = CellState::Initial;}}
+
+    /// Sets the cell to solved (Used for cell colors in terminal output)
+    fn set_newly_solved(&mut self) {self.state = CellState::New;}
+
+    /// Sets the cell to previously solved (Used for cell colors in terminal output)
+    fn set_previously_solved(&mut self) {if self.state == CellState::New {self.state = CellState::Solved;} }
+
+    fn set_guessed(&mut self) {self.state = CellState::Guess;}
+}
+
+//-------------------------------------
+// Game Board
+//   Collection of Game Cells in 2d grid
+//-------------------------------------
+#[derive(Debug)]
+pub struct GameBoard {
+    pub board: Vec< Vec< RefCell<GameCell> > >,
+    pub steps: Vec<Step>, // log of every placement and elimination made on this board
+    pub record_steps: bool, // turned off for boards only used to search, where the log would be thrown away
+    pub shape: Shape, // the size of the board and its boxes
+    pub constraints: Rc<Constraints>, // variant rules read from a constraint file, shared by every copy of the board
+    pub(crate) trail: Option<Trail>, // old copies of the cells written during a search, so it can back up
+    pub(crate) custom_conflict: Option<Contradiction>, // what a custom rule reported in the last propagation pass
+}
+impl GameBoard {
+
+    /// an empty classic 9x9 board
+    pub fn new() -> GameBoard {
+        GameBoard::with_shape(Shape::classic())
+    }
+
+    /// an empty board of any size
+    pub fn with_shape(shape:Shape) -> GameBoard {
+        let b = (0..shape.size).map(|_| {
+            (0..shape.size).map(|_| RefCell::new(GameCell::new(shape.size))).collect()
+        }).collect();
+        GameBoard{
+            board:b,
+            steps:Vec::new(),
+            record_steps:true,
+            shape,
+            constraints:Rc::new(Constraints::default()),
+            trail:None,
+            custom_conflict:None,
+        }
+    }
+
+    /// testing function to inject a board with a known solution
+    pub fn init_board_with_test_values(&mut self) {
+        self.init_board_from_string("091000203000002700705600000000713060009000000000500002000007304000060009000300015".to_string()); //solves in 8 steps with distribution
+    }
+
+    /// removes any cell possible values if that value already exists in it's row
+    pub fn set_possible_values_by_row(&mut self) {
+        for i in 0..self.shape.size {
+            self.set_possible_values_by_unit(Unit::Row(i));
+        }
+    }
+
+    /// removes any cell possible values if that value already exists in it's column
+    pub fn set_possible_values_by_col(&mut self) {
+        for i in 0..self.shape.size {
+            self.set_possible_values_by_unit(Unit::Col(i));
+        }
+    }
+
+    /// removes any cell possible values if that value already exists in its square
+    pub fn set_possible_values_by_square(&mut self) {
+        for i in 0..self.shape.size {
+            self.set_possible_values_by_unit(Unit::Square(i));
+        }
+    }
+
+    /// removes any cell possible values if that value already exists in one of the units added by the variants
+    fn set_possible_values_by_extra_units(&mut self) {
+        for unit in self.shape.extra_units() {
+            self.set_possible_values_by_unit(unit);
+        }
+    }
+
+    /// removes any cell possible values if that value is already placed a knight's or king's move away under the chess variants
+    fn set_possible_values_by_chess_moves(&mut self) {
+        for variant in self.shape.variants.clone() {
+            let distance = match variant {
+                Variant::AntiKnight => "a knight's move",
+                Variant::AntiKing => "a king's move",
+                Variant::X | Variant::Hyper | Variant::Disjoint => continue,
+            };
+            for source in self.shape.cells() {
+                let this_value = self.board[source.0][source.1].get_mut().value;
+                if this_value == 0 {
+                    continue;
+                }
+                let mut changed = Vec::new();
+                for cell in self.shape.move_peers(source, variant) {
+                    if self.remove_candidate(cell, this_value) {
+                        changed.push(cell);
+                    }
+                }
+                if !changed.is_empty() && self.record_steps {
+                    let reason = format!("{} is placed at {} so it is removed from {} which are {} away", this_value, cell_name(source), cell_list(&changed), distance);
+                    self.steps.push(Step::new(Technique::Elimination, changed, vec![this_value], reason));
+                }
+            }
+        }
+    }
+
+    /// removes every value placed in the unit from the possible values of the rest of the unit
+    fn set_possible_values_by_unit(&mut self, unit:Unit) {
+        let cells = self.shape.unit_cells(unit);
+        for &source in &cells {
+            let this_value = self.board[source.0][source.1].get_mut().value;
+            if this_value == 0 {
+                continue;
+            }
+            let mut changed = Vec::new();
+            for &cell in &cells {
+                if self.remove_candidate(cell, this_value) {
+                    changed.push(cell);
+                }
+            }
+            self.log_elimination(unit, source, this_value, changed);
+        }
+    }
+
+    /// records the cells that lost a possible value because it was already placed in one of their units
+    fn log_elimination(&mut self, unit:Unit, source:(usize,usize), value:usize, changed:Vec<(usize,usize)>) {
+        if changed.is_empty() || !self.record_steps {
+            return;
+        }
+        let reason = format!("{} is placed at {} so it is removed from {} in {}", value, cell_name(source), cell_list(&changed), unit);
+        self.steps.push(Step::new(Technique::Elimination, changed, vec![value], reason));
+    }
+
+    /// records a value that has only one possible cell left in a unit
+    fn log_hidden_single(&mut self, unit:Unit, cell:(usize,usize), value:usize) {
+        if !self.record_steps {
+            return;
+        }
+        self.cell_mut(cell).hidden_single = true;
+        let reason = format!("{} in {} at {}", value, unit, cell_name(cell));
+        self.steps.push(Step::new(Technique::HiddenSingle, vec![cell], vec![value], reason).in_unit(unit));
+    }
+
+    /// records a row/col segment whose cells can only hold as many values between them as there are cells
+    fn log_short_segment(&mut self, unit:Unit, segment:&[(usize,usize)], values:Vec<usize>, changed:Vec<(usize,usize)>) {
+        if changed.is_empty() || !self.record_steps {
+            return;
+        }
+        let reason = format!("{} can only hold {} between them so they are removed from {} in the rest of {} and its square",
+            cell_list(segment), digit_list(&values), cell_list(&changed), unit);
+        self.steps.push(Step::new(Technique::ShortSegment, changed, values, reason));
+    }
+
+    /// prints the board without color
+    pub fn print_board(&self) {
+        if self.has_outside_clues() {
+            return self.print_outside_board(|cell| cell.print(&self.shape));
+        }
+        if self.shape.is_jigsaw() || self.has_marks() {
+            return self.print_bordered_board(1, ' ', false, |c| Some(self.shape.square_of(c)), |_| None, |cell| cell.print(&self.shape));
+        }
+        for i in 0..self.shape.size {
+            for j in 0..self.shape.size {
+                self.board[i][j].borrow().print(&self.shape);
+                if self.shape.is_box_right_edge(j) {
+                    print!("|");
+                }
+            }
+            println!();
+            if self.shape.is_box_bottom_edge(i) {
+                println!("{}",self.shape.separator_line(1));
+            }
+        }
+        println!();
+    }
+
+    /// print the board using linux color escapes. Red for Initial cells and white&Bold for newly solved cells
+    pub fn print_color_board(&self) {
+        if self.has_outside_clues() {
+            return self.print_outside_board(|cell| cell.print_color(&self.shape));
+        }
+        if self.shape.is_jigsaw() || self.has_marks() {
+            return self.print_bordered_board(1, ' ', false, |c| Some(self.shape.square_of(c)), |_| None, |cell| cell.print_color(&self.shape));
+        }
+        for i in 0..self.shape.size {
+            for j in 0..self.shape.size {
+                self.board[i][j].borrow().print_color(&self.shape);
+                if self.shape.is_box_right_edge(j) {
+                    print!("|");
+                }
+            }
+            println!();
+            if self.shape.is_box_bottom_edge(i) {
+                println!("{}",self.shape.separator_line(1));
+            }
+        }
+        println!();
+    }
+
+    /// print all cells padded to the board size, if unsolved print the possible values remaining
+    fn print_detailed_board(&self) {
+        if !self.constraints.cages.is_empty() {
+            // killer boards show the cages with their sums instead of the boxes
+            let label = |c| self.constraints.cages.iter().find(|cage| cage.corner() == c).map(|cage| cage.sum.to_string());
+            return self.print_bordered_board(self.shape.size, '.', true, |c| self.cage_of(c), label, |cell| cell.print_detailed(&self.shape));
+        }
+        if self.shape.is_jigsaw() || self.has_marks() {
+            return self.print_bordered_board(self.shape.size, '.', false, |c| Some(self.shape.square_of(c)), |_| None, |cell| cell.print_detailed(&self.shape));
+        }
+        for i in 0..self.shape.size {
+            for j in 0..self.shape.size {
+                self.board[i][j].borrow().print_detailed(&self.shape);
+                print!(".");
+                if self.shape.is_box_right_edge(j) {
+                    print!("|");
+                }
+            }
+            println!();
+            if self.shape.is_box_bottom_edge(i) {
+                println!("{}",self.shape.separator_line(self.shape.size+1));
+            }
+        }
+        println!();
+    }
+
+    /// prints a board with borders between cells in different groups, such as jigsaw regions or killer cages. Each cell is
+    /// `width` characters wide and followed by a one character gap, which holds a '|' on a border. Lines between rows are
+    /// only drawn under borders. With `outline` groups are also closed off at the top and bottom of the board, and each
+    /// cell's label, e.g. a cage sum, is written in the line above it. Kropki dots and signs are drawn in the gap or line between their cells
+    fn print_bordered_board(&self, width:usize, gap:char, outline:bool, group_of:impl Fn((usize,usize))->Option<usize>,
+            label:impl Fn((usize,usize))->Option<String>, print_cell:impl Fn(&GameCell)) {
+        let size = self.shape.size;
+        // the group of the cell in row i, with rows off the top or bottom of the board in no group
+        let group = |i:usize, j:usize| if i == 0 || i > size {None} else {group_of((i-1,j))};
+        let right_border = |i:usize, j:usize| group(i,j) != group(i,j+1);
+        for i in 0..=size {
+            // the line above row i, using one based rows for `group`
+            if outline || (i > 0 && i < size) {
+                let mut line = String::new();
+                for j in 0..size {
+                    let across = group(i,j) != group(i+1,j);
+                    let mut segment: String = (if across {"-"} else {" "}).repeat(width);
+                    if let Some(text) = label((i,j)).filter(|_| i < size) {
+                        segment = text.chars().chain(segment.chars().skip(text.chars().count())).take(width).collect();
+                    }
+                    if let Some(dot) = (i > 0).then(|| self.mark_between((i-1,j), (i,j))).flatten() {
+                        segment = segment.chars().enumerate().map(|(k,c)| if k == width/2 {dot} else {c}).collect();
+                    }
+                    line.push_str(&segment);
+                    if j+1 < size {
+                        let across = across || group(i,j+1) != group(i+1,j+1);
+                        let down = (i > 0 && right_border(i,j)) || (i < size && right_border(i+1,j));
+                        line.push(match (across,down) {
+                            (true,true) => '+',
+                            (true,false) => '-',
+                            (false,true) => '|',
+                            (false,false) => ' ',
+                        });
+                    }
+                }
+                println!("{}",line);
+            }
+            if i == size {
+                break;
+            }
+            for j in 0..size {
+                print_cell(&self.board[i][j].borrow());
+                if j+1 < size {
+                    let between = self.mark_between((i,j), (i,j+1)).unwrap_or(if right_border(i+1,j) {'|'} else {gap});
+                    print!("{}", between);
+                }
+            }
+            println!();
+        }
+        println!();
+    }
+
+    /// true if there are dots or greater-than signs to draw between cells
+    fn has_marks(&self) -> bool {
+        !self.constraints.dots.is_empty() || !self.constraints.signs.is_empty()
+    }
+
+    /// the dot or sign drawn between two neighbouring cells, if there is one
+    fn mark_between(&self, a:(usize,usize), b:(usize,usize)) -> Option<char> {
+        self.dot_between(a, b).or_else(|| self.sign_between(a, b))
+    }
+
+    /// true if there are sandwich or little killer clues to print around the edge of a board with regular boxes
+    fn has_outside_clues(&self) -> bool {
+        let clues = !self.constraints.sandwiches.is_empty() || !self.constraints.little_killers.is_empty();
+        clues && !self.shape.is_jigsaw()
+    }
+
+    /// prints the board with the sandwich and little killer clues around its edge. Every cell is three characters wide so two
+    /// digit clues fit, and a little killer's sum is followed by its arrow. The bottom and right edges are only printed when they hold clues
+    fn print_outside_board(&self, print_cell:impl Fn(&GameCell)) {
+        let size = self.shape.size as isize;
+        let clue = |spot:(isize,isize)| format!("{:>3}", self.outside_clue(spot).unwrap_or_default());
+        let bottom = (-1..=size).any(|j| self.outside_clue((size,j)).is_some());
+        let right = (-1..=size).any(|i| self.outside_clue((i,size)).is_some());
+        let print_edge = |i:isize| {
+            let mut line = clue((i,-1));
+            for j in 0..self.shape.size {
+                line.push_str(&clue((i,j as isize)));
+                if self.shape.is_box_right_edge(j) {
+                    line.push(' ');
+                }
+            }
+            if right {
+                line.push_str(&clue((i,size)));
+            }
+            println!("{}", line.trim_end());
+        };
+        print_edge(-1);
+        for i in 0..self.shape.size {
+            print!("{}", clue((i as isize,-1)));
+            for j in 0..self.shape.size {
+                print!(" ");
+                print_cell(&self.board[i][j].borrow());
+                print!(" ");
+                if self.shape.is_box_right_edge(j) {
+                    print!("|");
+                }
+            }
+            if right {
+                print!("{}", clue((i as isize,size)));
+            }
+            println!();
+            if self.shape.is_box_bottom_edge(i) {
+                println!("   {}", self.shape.separator_line(3));
+            }
+        }
+        if bottom {
+            print_edge(size);
+        }
+        println!();
+    }
+
+    /// checks all unsolved cells to see if there is only one possible value remaining and updates the cell to that value and marks solved. Boolean return value indicates changes were made
+    pub fn set_values_from_possible(&mut self) -> bool {
+        let mut changes_made = false;
+        for (i,j) in self.shape.cells() {
+            if self.board[i][j].borrow().possible_values.len() == 1 {
+                self.cell_mut((i,j)).check_possible();
+                changes_made = true;
+                // hidden singles were already logged when the other possible values were removed
+                let cell = self.board[i][j].get_mut();
+                if self.record_steps && !cell.hidden_single {
+                    let value = cell.value;
+                    let reason = format!("{} can only be {}", cell_name((i,j)), value);
+                    self.steps.push(Step::new(Technique::NakedSingle, vec![(i,j)], vec![value], reason));
+                }
+            }
+        }
+        changes_made
+    }
+
+    /// Runs every loop of the algorithm to set the newly solved cells to just solved. Used for coloring newly solved cells differently
+    pub fn set_previously_solved_cells(&mut self) {
+        for (i,j) in self.shape.cells() {
+            if self.board[i][j].borrow().state == CellState::New {
+                self.cell_mut((i,j)).set_previously_solved();
+            }
+        }
+    }
+
+    /// Used to declare the current state of the board the initial state. All solved cells will be colored accordingly
+    pub fn set_initial_cells(&mut self) {
+        for (i,j) in self.shape.cells() {
+            self.board[i][j].get_mut().set_initial();
+        }
+    }
+
+    /// takes a string with one character per cell and initializes the board with the appropriate values. 0 (or '.') indicates unsolved.
+    /// A classic board is resized to the usual shape for a puzzle with a different number of cells
+    pub fn init_board_from_string(&mut self,in_str:String) {
+        let cells = in_str.chars().count();
+        if cells != self.shape.cell_count() && self.shape == Shape::classic() {
+            if let Some(shape) = Shape::from_cell_count(cells) {
+                let record_steps = self.record_steps;
+                *self = GameBoard::with_shape(shape);
+                self.record_steps = record_steps;
+            }
+        }
+        let value_vector = convert_string_to_vector(&in_str, &self.shape);
+        for (k,(i,j)) in self.shape.cells().enumerate() {
+            self.board[i][j].get_mut().set_value( value_vector[k] );
+        }
+        self.set_initial_cells();
+    }
+
+    /// applies the jigsaw regions, variants, and constraint file given on the command line. A region map puts its regions in place of the boxes
+    pub fn set_rules(&mut self, regions:Option<String>, variants:&[Variant], constraints:Option<PathBuf>) {
+        if let Some(regions) = regions {
+            self.shape = self.shape.clone().with_regions(&regions).unwrap_or_else(|e| panic!("{}",e));
+        }
+        self.shape = self.shape.clone().with_variants(variants);
+        if let Some(constraints) = constraints {
+            self.constraints = Rc::new(Constraints::from_file(&constraints, &self.shape).unwrap_or_else(|e| panic!("{}",e)));
+        }
+    }
+
+    /// initializes the board from whichever of the string or file command line arguments was supplied
+    pub fn init_board_from_args(&mut self,in_string:Option<String>,in_file:Option<PathBuf>) {
+        if in_string.is_some() && in_file.is_some() {
+            panic!("you can only supply one puzzle to solve at a time");
+        } else if let Some(in_string) = in_string {
+            self.init_board_from_string(in_string);
+        } else if let Some(in_file) = in_file {
+            self.init_board_from_file(in_file);
+        } else {
+            panic!("you must supply a puzzle to solve");
+        }
+        self.set_initial_cells();
+    }
+
+    fn init_board_from_file(&mut self,in_file:PathBuf) {
+        let f = File::open( in_file );
+        if f.is_err() {
+            panic!("Error opening file");
+        }
+        let mut contents = String::new();
+        let mut o = f.unwrap();
+        o.read_to_string(&mut contents).unwrap_or_else(|e| panic!("Error reading file: {}", e));
+        // keep the values and the '.' used for unsolved cells, dropping spaces and the lines drawn between boxes
+        let t: String = contents.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '.').collect();
+        if t.chars().count() == self.shape.cell_count() || Shape::from_cell_count(t.chars().count()).is_some() {
+            self.init_board_from_string(t);
+        } else {
+            panic!("Invalid file contents:\n{}",t);
+        }
+    }
+    /// checks rows, columns, squares, and the variants' units to see if there are any possible values that appear only once
+    pub fn set_possible_values_by_distribution(&mut self) {
+        let size = self.shape.size;
+        let units: Vec<Unit> = (0..size).map(Unit::Row)
+            .chain((0..size).map(Unit::Col))
+            .chain((0..size).map(Unit::Square))
+            .chain(self.shape.extra_units())
+            .collect();
+        for unit in units {
+            let cells = self.shape.unit_cells(unit);
+            let mut distribution_vector: Vec<usize> = vec![0; size];
+            for &(i,j) in &cells {
+                if self.board[i][j].borrow().deref().value == 0 {
+                    increment_values_by_index(&mut distribution_vector, 
+                        &self.board[i][j].borrow().possible_values)
+                }
+            }
+            for (k, x) in (1..).zip(distribution_vector) { //k is used to track what value things are
+                if x == 1 {
+                    for &(i,j) in &cells {
+                        if self.board[i][j].borrow().deref().possible_values.contains(&k) &&
+                            self.cell_mut((i,j)).keep_only_possible_cell_value(k) {
+                            self.log_hidden_single(unit, (i,j), k);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// check where each row and column crosses a square to see if the cells there can only hold as many values as there are cells,
+    /// and remove those values along the rest of the row/col and square
+    fn set_possible_values_by_short_segments(&mut self) {
+        let size = self.shape.size;
+        let lines: Vec<Unit> = (0..size).map(Unit::Row).chain((0..size).map(Unit::Col)).collect();
+        for line in lines {
+            let line_cells = self.shape.unit_cells(line);
+            let mut squares: Vec<usize> = line_cells.iter().map(|&c| self.shape.square_of(c)).collect();
+            squares.sort();
+            squares.dedup();
+            for sq in squares {
+                let square_cells = self.shape.unit_cells(Unit::Square(sq));
+                let segment: Vec<(usize,usize)> = line_cells.iter().copied().filter(|c| square_cells.contains(c)).collect();
+                let domain = self.domain(&segment);
+                if domain.len() != segment.len() {
+                    continue;
+                }
+                // the rest of the line first, then the other lines of the square
+                let targets: Vec<(usize,usize)> = line_cells.iter().chain(&square_cells).copied()
+                    .filter(|c| !segment.contains(c))
+                    .collect();
+                let mut changed = Vec::new();
+                for &val in &domain {
+                    for &cell in &targets {
+                        if self.remove_candidate(cell, val) && !changed.contains(&cell) {
+                            changed.push(cell);
+                        }
+                    }
+                }
+                self.log_short_segment(line, &segment, sorted(domain), changed);
+            }
+        }
+    }
+
+    /// every value the cells hold or could hold, without duplicates
+    fn domain(&self, cells:&[(usize,usize)]) -> Vec<usize> {
+        let mut returned: Vec<usize> = Vec::new();
+        for &(i,j) in cells {
+            let cell = self.board[i][j].borrow();
+            if cell.value != 0 {returned.push(cell.value);} else {returned.extend(cell.possible_values.iter());}
+        }
+        returned.sort();
+        returned.dedup();
+        returned
+    }
+
+    /// function to check if there are no unsolved cells remaining
+    pub fn is_solved(&self)->bool{
+        self.shape.cells().all(|(i,j)| self.board[i][j].borrow().deref().value != 0)
+    }
+
+    /// runs every constraint propagation pass once then sets any cells left with a single possible value. Boolean return value indicates cells were solved
+    pub fn propagate(&mut self) -> bool {
+        self.set_possible_values_by_row();
+        self.set_possible_values_by_col();
+        self.set_possible_values_by_square();
+        self.set_possible_values_by_extra_units();
+        self.set_possible_values_by_chess_moves();
+        self.set_possible_values_by_cages();
+        self.set_possible_values_by_distribution();
+        self.set_possible_values_by_short_segments();
+        self.set_possible_values_by_cage_sums();
+        self.set_possible_values_by_45_rule();
+        self.set_possible_values_by_dots();
+        self.set_possible_values_by_thermos();
+        self.set_possible_values_by_arrows();
+        self.set_possible_values_by_lines();
+        self.set_possible_values_by_sandwiches();
+        self.set_possible_values_by_little_killers();
+        self.set_possible_values_by_parity();
+        self.set_possible_values_by_signs();
+        if self.set_possible_values_by_custom() {
+            // is_unsolvable picks up the broken rule, so there's no point placing values
+            return false;
+        }
+
+        self.set_values_from_possible()
+    }
+
+    /// runs through the constraint propagation algorithm once and returns the result
+    pub fn hint(&mut self) {
+        self.propagate();
+        steps::print_steps(&self.steps);
+        println!();
+        self.print_color_board();
+        println!();
+        self.print_detailed_board();
+    }
+
+    /// Will return the current game board as a string with one character per cell, using the board's empty symbol for unsolved values.
+    pub fn board_to_string (&self)->String {
+        self.shape.cells().map(|(i,j)| self.shape.symbol(self.board[i][j].borrow().deref().value)).collect::<String>()
+    }
+
+    /// a copy of the board for searching, which doesn't record steps
+    pub fn search_clone (&self)->GameBoard{
+        let mut returned = self.clone();
+        returned.record_steps = false;
+        returned
+    }
+
+    /// the cell to write to. Writes go through here so a search can undo them
+    pub fn cell_mut(&mut self, (i,j):(usize,usize)) -> &mut GameCell {
+        let cell = self.board[i][j].get_mut();
+        if let Some(trail) = &mut self.trail {
+            trail.save(i*self.shape.size + j, cell);
+        }
+        cell
+    }
+
+    /// removes a possible value from an unsolved cell. Returns true if the value was there
+    pub fn remove_candidate(&mut self, (i,j):(usize,usize), v:usize) -> bool {
+        let cell = self.board[i][j].borrow();
+        if cell.value != 0 || !cell.possible_values.contains(&v) {
+            return false;
+        }
+        drop(cell);
+        self.cell_mut((i,j)).remove_possible_cell_value(v)
+    }
+
+    /// Will return the row and column of the first unsolved gamecell with the fewest possible values. will return none if used on a solved board.
+    fn get_smallest_possible_gamecell_by_idx (&self) -> Option<(usize,usize)> {
+        let mut smallest = 0;
+        for (i,j) in self.shape.cells() {
+            if smallest !=0 {
+                let tmp = self.board[i][j].borrow().deref().possible_values.len();
+                if tmp != 0 && tmp < smallest {smallest=tmp;}
+            } else {
+                smallest = self.board[i][j].borrow().deref().possible_values.len();
+            }
+        }
+        if smallest == 0 {return None;}
+        self.shape.cells().find(|&(i,j)| smallest == self.board[i][j].borrow().deref().possible_values.len())
+    }
+
+    /// check to see if there are any cells that are not assigned and have no potential values, or any value placed twice in a row, column, or square
+    fn is_unsolvable (&self) -> bool{
+        for (i,j) in self.shape.cells() {
+            if self.board[i][j].borrow().possible_values.is_empty() && self.board[i][j].borrow().deref().value == 0 {
+                return true;
+            }
+        }
+        self.has_conflicts()
+    }
+
+    /// check to see if any value appears more than once in a row, column, square, or one of the variants' units,
+    /// a chess move apart under the chess variants, or a killer cage, Kropki dot, XV, line, outside clue, or custom rule is broken
+    fn has_conflicts (&self) -> bool {
+        for (i,j) in self.shape.cells() {
+            let value = self.board[i][j].borrow().value;
+            if value != 0 && self.shape.peers((i,j)).iter().any(|&(a,b)| self.board[a][b].borrow().value == value) {
+                return true;
+            }
+        }
+        self.has_cage_conflicts() || self.has_dot_conflicts() || self.has_line_conflicts() || self.has_outside_conflicts() ||
+            self.has_compare_conflicts() || self.has_custom_conflicts()
+    }
+    
+    /// primary solve loop. Will loopt through using constraint propogation until the board is solved or until there are no moves left. It will then
+    /// search a copy of the board for a solution once, and make the guesses that led to it each time propagation gets stuck
+    pub fn solve_loop(&mut self,verbose:bool) -> bool {
+        self.solve_loop_with(verbose, &SolveOptions::default()).status == Status::Solved
+    }
+
+    /// the solve loop with limits on the guessing. When it gives up the board keeps the values found so far
+    pub fn solve_loop_with(&mut self, verbose:bool, options:&SolveOptions) -> SolveResult {
+        let mut budget = Budget::new(options.clone());
+        let mut solution: Option<(GameBoard, std::vec::IntoIter<_>)> = None; // the solved copy and the guesses left to make
+        loop {
+            let round_start = self.steps.len();
+            let mut updated = self.propagate();
+            if !updated && !self.is_unsolvable() {
+                if solution.is_none() {
+                    let mut found = self.search_clone();
+                    match found.search_path(|_| {}, &mut budget) {
+                        Some(path) => solution = Some((found, path.into_iter())),
+                        None => {
+                            let status = budget.stopped.map_or(Status::NoSolution, Status::GaveUp);
+                            return budget.finish(status);
+                        }
+                    }
+                }
+                // propagation gets stuck in the same places the search did, so the next guess on its path is the one to make
+                let (found, path) = solution.as_mut().unwrap();
+                match path.next() {
+                    Some((target,value)) => {
+                        self.cell_mut(target).set_value(value);
+                        self.cell_mut(target).set_guessed();
+                        let reason = format!("{} at {} leads to a solution", value, cell_name(target));
+                        self.steps.push(Step::new(Technique::Guess, vec![target], vec![value], reason));
+                    }
+                    None => {
+                        // out of guesses but still stuck, so the rest is filled in from the solution and logged like the guesses
+                        for (i,j) in self.shape.cells() {
+                            let value = found.board[i][j].borrow().value;
+                            if self.board[i][j].borrow().value == 0 {
+                                self.cell_mut((i,j)).set_value(value);
+                                self.cell_mut((i,j)).set_guessed();
+                                let reason = format!("{} at {} is taken from the solution the search found", value, cell_name((i,j)));
+                                self.steps.push(Step::new(Technique::Guess, vec![(i,j)], vec![value], reason));
+                            }
+                        }
+                    }
+                }
+                updated = true;
+            }
+            if verbose && updated {
+                steps::print_steps(&self.steps[round_start..]);
+                self.print_color_board();
+            }
+            self.set_previously_solved_cells();
+            if self.is_unsolvable() {return budget.finish(Status::NoSolution);}
+            if self.is_solved() {return budget.finish(Status::Solved);}
+        }
+    }
+    
+    /// counts the solutions of the board using constraint propagation and guessing. Stops counting once `limit` solutions are found
+    fn count_solutions(&self, limit:usize) -> usize {
+        self.search_clone().search(limit, |_| {}, &mut Budget::unlimited())
+    }
+
+    /// checks that the board has exactly one solution
+    pub fn has_unique_solution(&self) -> bool {
+        self.count_solutions(2) == 1
+    }
+
+    /// Solve using the constraint propogation algorithm.
+    pub fn solve(&mut self,verbose:bool) -> bool {
+        self.solve_with(verbose, &SolveOptions::default()).status == Status::Solved
+    }
+
+    /// Solve with limits on the guessing, saying why if it gives up
+    pub fn solve_with(&mut self, verbose:bool, options:&SolveOptions) -> SolveResult {
+        let result = self.solve_loop_with(verbose, options);
+        match result.status {
+            Status::GaveUp(limit) => println!("Gave up, {} after {}", limit, result.stats),
+            _ if verbose && result.stats.nodes > 0 => println!("Searched {}", result.stats),
+            _ => {}
+        }
+        if result.status != Status::Solved {
+            self.print_detailed_board();
+        }
+        if !verbose {
+            self.print_board();
+        }
+        result
+    }
+
+}
+impl Clone for GameBoard {
+    /// copies the cells, shape, and rules but not the step log
+    fn clone (&self)->GameBoard{
+        let mut returned = GameBoard::with_shape(self.shape.clone());
+        returned.board = self.board.clone();
+        returned.record_steps = self.record_steps;
+        returned.constraints = Rc::clone(&self.constraints);
+        returned
+    }
+}
+impl Default for GameBoard {
+    fn default() -> GameBoard {
+        GameBoard::new()
+    }
+}
+
+
+//-----------------------------------------------------------------------------
+// Helper functions
+//     (All written by chatgpt)
+//-----------------------------------------------------------------------------
+fn convert_string_to_vector(input: &str, shape: &Shape) -> Vec<usize> {
+    if input.chars().count() != shape.cell_count() {
+        panic!("Input string must be exactly {} characters long", shape.cell_count());
+    }
+
+    let mut result = Vec::with_capacity(shape.cell_count());
+
+    for c in input.chars() {
+        match shape.value_of(c) {
+            Some(value) => result.push(value),
+            None => panic!("Invalid character found in input: {}", c),
+        }
+    }
+
+    result
+}
+
+fn increment_values_by_index(base_vector: &mut [usize], indices_to_increment: &[usize]) {
+    for &index in indices_to_increment {
+        let adjusted_index = index.checked_sub(1).unwrap_or_else(|| {
+            panic!("Index {} is out of bounds for vector of length {}", index, base_vector.len());
+        });
+
+        if adjusted_index < base_vector.len() {
+            base_vector[adjusted_index] += 1;
+        } else {
+            panic!("Index {} is out of bounds for vector of length {}", index, base_vector.len());
+        }
+    }
+}
+
+fn sorted(mut values:Vec<usize>) -> Vec<usize> {
+    values.sort();
+    values
+}
@@ -0,0 +1,375 @@
+//-----------------------------------------------------------------------------
+// Line Constraints
+//   Constraints drawn as a path of touching cells, including diagonally.
+//   A thermometer's values rise from its bulb to its tip, and the values
+//   along an arrow add up to the value in its circle. Both are solved by
+//   keeping each cell's smallest and largest possible values in bounds.
+//   Other lines only need a rule for which values each cell can keep given
+//   the options of the rest of the line: German whispers, renban, and
+//   palindromes so far
+//-----------------------------------------------------------------------------
+
+use std::rc::Rc;
+use crate::GameBoard;
+use crate::killer::has_matching;
+use crate::steps::{cell_list, cell_name, digit_list, Step, Technique};
+
+/// Checks that each cell of a path touches the next one, including diagonally
+fn check_path(cells:&[(usize,usize)]) -> Result<(),String> {
+    for pair in cells.windows(2) {
+        let (a,b) = (pair[0], pair[1]);
+        if a.0.abs_diff(b.0) > 1 || a.1.abs_diff(b.1) > 1 {
+            return Err(format!("{} and {} are not next to each other", cell_name(a), cell_name(b)));
+        }
+    }
+    Ok(())
+}
+
+/// Values strictly rise along the cells, starting from the bulb
+#[derive(Clone,Debug)]
+pub struct Thermo {
+    pub cells: Vec<(usize,usize)>,
+}
+impl Thermo {
+    pub fn new(cells:Vec<(usize,usize)>, size:usize) -> Result<Thermo,String> {
+        if cells.len() < 2 {
+            return Err("a thermometer needs at least two cells".to_string());
+        }
+        if cells.len() > size {
+            return Err(format!("a thermometer can't be longer than {} cells", size));
+        }
+        check_path(&cells)?;
+        Ok(Thermo{cells})
+    }
+}
+
+/// The values along the arrow add up to the value in its circle
+#[derive(Clone,Debug)]
+pub struct Arrow {
+    pub circle: (usize,usize),
+    pub cells: Vec<(usize,usize)>,
+}
+impl Arrow {
+    pub fn new(circle:(usize,usize), cells:Vec<(usize,usize)>) -> Result<Arrow,String> {
+        if cells.is_empty() {
+            return Err("an arrow needs a circle and at least one cell".to_string());
+        }
+        let mut path = vec![circle];
+        path.extend(&cells);
+        check_path(&path)?;
+        Ok(Arrow{circle,cells})
+    }
+}
+
+/// The lines that are solved by a rule for the values each cell can keep
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum LineKind {
+    Whisper, // German whispers, neighbours on the line are at least half the board size apart, 5 on a 9x9 board
+    Renban, // the line holds a run of consecutive values in any order
+    Palindrome, // the line reads the same from either end
+}
+impl LineKind {
+    fn technique(&self) -> Technique {
+        match self {
+            LineKind::Whisper => Technique::Whisper,
+            LineKind::Renban => Technique::Renban,
+            LineKind::Palindrome => Technique::Palindrome,
+        }
+    }
+
+    /// for each cell, the values from its options that fit the rule with some options of the rest of the line
+    fn allowed(&self, options:&[Vec<usize>], size:usize) -> Vec<Vec<usize>> {
+        let n = options.len();
+        match self {
+            LineKind::Whisper => {
+                let gap = whisper_gap(size);
+                (0..n).map(|k| options[k].iter().copied().filter(|&v| {
+                    let fits = |other:&Vec<usize>| other.iter().any(|&w| v.abs_diff(w) >= gap);
+                    (k == 0 || fits(&options[k-1])) && (k+1 == n || fits(&options[k+1]))
+                }).collect()).collect()
+            }
+            LineKind::Renban => {
+                let mut allowed = vec![Vec::new(); n];
+                for low in 1..=size+1-n {
+                    let run: Vec<Vec<usize>> = options.iter().map(|o| o.iter().copied().filter(|v| (low..low+n).contains(v)).collect()).collect();
+                    if !has_matching(&run) {continue;}
+                    for (k,values) in run.into_iter().enumerate() {
+                        for value in values {
+                            if !allowed[k].contains(&value) {allowed[k].push(value);}
+                        }
+                    }
+                }
+                allowed
+            }
+            LineKind::Palindrome => (0..n).map(|k| options[k].iter().copied().filter(|v| options[n-1-k].contains(v)).collect()).collect(),
+        }
+    }
+
+    /// true if the placed values, 0 for unsolved, break the rule
+    fn is_broken(&self, values:&[usize], size:usize) -> bool {
+        let n = values.len();
+        match self {
+            LineKind::Whisper => values.windows(2).any(|pair| pair[0] != 0 && pair[1] != 0 && pair[0].abs_diff(pair[1]) < whisper_gap(size)),
+            LineKind::Renban => {
+                let mut placed: Vec<usize> = values.iter().copied().filter(|&v| v != 0).collect();
+                placed.sort();
+                placed.windows(2).any(|pair| pair[0] == pair[1]) || placed.last().zip(placed.first()).is_some_and(|(high,low)| high - low >= n)
+            }
+            LineKind::Palindrome => (0..n).any(|k| values[k] != 0 && values[n-1-k] != 0 && values[k] != values[n-1-k]),
+        }
+    }
+
+    fn describe(&self, cells:&[(usize,usize)], size:usize) -> String {
+        match self {
+            LineKind::Whisper => format!("neighbours on the German whispers line {} are at least {} apart", cell_list(cells), whisper_gap(size)),
+            LineKind::Renban => format!("the renban line {} holds {} consecutive values", cell_list(cells), cells.len()),
+            LineKind::Palindrome => format!("the palindrome line {} reads the same from either end", cell_list(cells)),
+        }
+    }
+}
+
+/// How far apart neighbours on a German whispers line must be, 5 on a 9x9 board
+pub fn whisper_gap(size:usize) -> usize {
+    size.div_ceil(2)
+}
+
+#[derive(Clone,Debug)]
+pub struct Line {
+    pub kind: LineKind,
+    pub cells: Vec<(usize,usize)>,
+}
+impl Line {
+    pub fn new(kind:LineKind, cells:Vec<(usize,usize)>, size:usize) -> Result<Line,String> {
+        if cells.len() < 2 {
+            return Err("a line needs at least two cells".to_string());
+        }
+        if kind == LineKind::Renban && cells.len() > size {
+            return Err(format!("a renban line can't be longer than {} cells", size));
+        }
+        check_path(&cells)?;
+        Ok(Line{kind,cells})
+    }
+}
+
+impl GameBoard {
+    /// the value of a solved cell, or the possible values of an unsolved one
+    pub fn options(&self, cell:(usize,usize)) -> Vec<usize> {
+        let cell = self.board[cell.0][cell.1].borrow();
+        if cell.value != 0 {vec![cell.value]} else {cell.possible_values.clone()}
+    }
+
+    /// the smallest and largest value a cell can hold, or None if it has no options left
+    pub fn bounds(&self, cell:(usize,usize)) -> Option<(usize,usize)> {
+        let options = self.options(cell);
+        Some((*options.iter().min()?, *options.iter().max()?))
+    }
+
+    /// removes the possible values of each cell outside its bounds, logging the step. Boolean return value indicates changes were made
+    pub fn restrict_bounds(&mut self, cells:&[(usize,usize)], bounds:&[(usize,usize)], technique:Technique, description:String) -> bool {
+        let mut open = Vec::new();
+        let mut allowed: Vec<Vec<usize>> = Vec::new();
+        for (&(i,j), &(low,high)) in cells.iter().zip(bounds) {
+            let cell = self.board[i][j].borrow();
+            if cell.value == 0 {
+                open.push((i,j));
+                allowed.push(cell.possible_values.iter().copied().filter(|v| (low..=high).contains(v)).collect());
+            }
+        }
+        let (changed, removed) = self.restrict(&open, &allowed);
+        if changed.is_empty() {
+            return false;
+        }
+        if self.record_steps {
+            let reason = format!("{}, so {} are removed from {}", description, digit_list(&removed), cell_list(&changed));
+            self.steps.push(Step::new(technique, changed, removed, reason));
+        }
+        true
+    }
+
+    /// keeps the values of every thermometer rising from bulb to tip
+    pub fn set_possible_values_by_thermos(&mut self) {
+        for k in 0..self.constraints.thermos.len() {
+            self.filter_thermo(k);
+        }
+    }
+
+    /// each cell of a thermometer is at least one more than the smallest value of the cell before it
+    /// and at least one less than the largest value of the cell after it. Boolean return value indicates changes were made
+    pub fn filter_thermo(&mut self, k:usize) -> bool {
+        let constraints = Rc::clone(&self.constraints);
+        let thermo = &constraints.thermos[k];
+        let Some(mut bounds) = thermo.cells.iter().map(|&c| self.bounds(c)).collect::<Option<Vec<(usize,usize)>>>() else {
+            return false;
+        };
+        for n in 1..bounds.len() {
+            bounds[n].0 = bounds[n].0.max(bounds[n-1].0 + 1);
+        }
+        for n in (0..bounds.len()-1).rev() {
+            bounds[n].1 = bounds[n].1.min(bounds[n+1].1.saturating_sub(1));
+        }
+        let description = format!("the thermometer {} rises from its bulb at {}", cell_list(&thermo.cells), cell_name(thermo.cells[0]));
+        self.restrict_bounds(&thermo.cells, &bounds, Technique::Thermo, description)
+    }
+
+    /// keeps the circle of every arrow within the sums its cells can make, and the cells within what the circle allows
+    pub fn set_possible_values_by_arrows(&mut self) {
+        for k in 0..self.constraints.arrows.len() {
+            self.filter_arrow(k);
+        }
+    }
+
+    /// the circle lies between the smallest and largest sums of the arrow, and each cell of the arrow can't be so large the
+    /// rest can't fit under the circle, or so small the rest can't make it up. Boolean return value indicates changes were made
+    pub fn filter_arrow(&mut self, k:usize) -> bool {
+        let constraints = Rc::clone(&self.constraints);
+        let arrow = &constraints.arrows[k];
+        let Some(circle) = self.bounds(arrow.circle) else {
+            return false;
+        };
+        let Some(cells) = arrow.cells.iter().map(|&c| self.bounds(c)).collect::<Option<Vec<(usize,usize)>>>() else {
+            return false;
+        };
+        let low: usize = cells.iter().map(|b| b.0).sum();
+        let high: usize = cells.iter().map(|b| b.1).sum();
+        let mut bounds = vec![(circle.0.max(low), circle.1.min(high))];
+        for &(min, max) in &cells {
+            let rest_low = low - min;
+            let rest_high = high - max;
+            bounds.push((min.max(circle.0.saturating_sub(rest_high)), max.min(circle.1.saturating_sub(rest_low))));
+        }
+        let mut path = vec![arrow.circle];
+        path.extend(&arrow.cells);
+        let description = format!("the arrow {} adds up to the circle at {}", cell_list(&arrow.cells), cell_name(arrow.circle));
+        self.restrict_bounds(&path, &bounds, Technique::Arrow, description)
+    }
+
+    /// keeps only the possible values that fit the rule of every German whispers, renban, and palindrome line
+    pub fn set_possible_values_by_lines(&mut self) {
+        for k in 0..self.constraints.lines.len() {
+            self.filter_line(k);
+        }
+    }
+
+    /// applies the first line of the technique's kind that makes progress. Boolean return value indicates changes were made
+    pub fn apply_first_line(&mut self, technique:Technique) -> bool {
+        (0..self.constraints.lines.len()).any(|k| self.constraints.lines[k].kind.technique() == technique && self.filter_line(k))
+    }
+
+    /// removes the possible values of a line that don't fit its rule. Boolean return value indicates changes were made
+    pub fn filter_line(&mut self, k:usize) -> bool {
+        let constraints = Rc::clone(&self.constraints);
+        let line = &constraints.lines[k];
+        let options: Vec<Vec<usize>> = line.cells.iter().map(|&c| self.options(c)).collect();
+        let allowed = line.kind.allowed(&options, self.shape.size);
+        // solved cells have no possible values left to remove
+        let (changed, removed) = self.restrict(&line.cells, &allowed);
+        if changed.is_empty() {
+            return false;
+        }
+        if self.record_steps {
+            let reason = format!("{}, so {} are removed from {}", line.kind.describe(&line.cells, self.shape.size), digit_list(&removed), cell_list(&changed));
+            self.steps.push(Step::new(line.kind.technique(), changed, removed, reason));
+        }
+        true
+    }
+
+    /// check to see if any thermometer doesn't rise, any arrow can't add up to its circle, or any other line breaks its rule
+    pub fn has_line_conflicts(&self) -> bool {
+        let value = |(i,j):(usize,usize)| self.board[i][j].borrow().value;
+        let thermo_broken = self.constraints.thermos.iter().any(|thermo| {
+            let placed: Vec<(usize,usize)> = thermo.cells.iter().enumerate().map(|(n,&c)| (n, value(c))).filter(|&(_,v)| v != 0).collect();
+            placed.windows(2).any(|pair| pair[1].1 < pair[0].1 + (pair[1].0 - pair[0].0))
+        });
+        let arrow_broken = self.constraints.arrows.iter().any(|arrow| {
+            let total: usize = arrow.cells.iter().map(|&c| value(c)).sum();
+            let circle = value(arrow.circle);
+            let complete = arrow.cells.iter().all(|&c| value(c) != 0);
+            circle != 0 && (total > circle || (complete && total != circle))
+        });
+        let line_broken = self.constraints.lines.iter().any(|line| {
+            let values: Vec<usize> = line.cells.iter().map(|&c| value(c)).collect();
+            line.kind.is_broken(&values, self.shape.size)
+        });
+        thermo_broken || arrow_broken || line_broken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::Constraints;
+
+    fn board(puzzle:&str, rules:&str) -> GameBoard {
+        let mut board = GameBoard::new();
+        board.init_board_from_string(puzzle.to_string());
+        board.constraints = Rc::new(Constraints::parse(rules, &board.shape).unwrap());
+        board
+    }
+
+    fn empty() -> String {
+        "0".repeat(81)
+    }
+
+    #[test]
+    fn bounds_of_solved_open_and_broken_cells() {
+        let mut puzzle = empty();
+        puzzle.replace_range(0..1, "5");
+        let mut board = board(&puzzle, "");
+        assert_eq!(board.bounds((0,0)), Some((5,5)));
+        assert_eq!(board.bounds((0,1)), Some((1,9)));
+        for value in 1..=9 {
+            board.remove_candidate((0,1), value);
+        }
+        assert_eq!(board.bounds((0,1)), None);
+    }
+
+    #[test]
+    fn restrict_bounds_skips_solved_cells() {
+        let mut puzzle = empty();
+        puzzle.replace_range(0..1, "5");
+        let mut board = board(&puzzle, "");
+        assert!(board.restrict_bounds(&[(0,0),(0,1)], &[(1,3),(2,4)], Technique::Thermo, String::new()));
+        assert_eq!(board.options((0,0)), vec![5]);
+        assert_eq!(board.options((0,1)), vec![2,3,4]);
+        assert!(!board.restrict_bounds(&[(0,1)], &[(1,9)], Technique::Thermo, String::new()));
+    }
+
+    #[test]
+    fn thermo_rises_from_the_bulb() {
+        let mut board = board(&empty(), "thermo r1c1 r1c2 r1c3");
+        assert!(board.filter_thermo(0));
+        assert_eq!(board.bounds((0,0)), Some((1,7)));
+        assert_eq!(board.bounds((0,1)), Some((2,8)));
+        assert_eq!(board.bounds((0,2)), Some((3,9)));
+        assert!(!board.filter_thermo(0));
+    }
+
+    #[test]
+    fn thermo_around_a_given() {
+        let mut puzzle = empty();
+        puzzle.replace_range(1..2, "5");
+        let mut board = board(&puzzle, "thermo r1c1 r1c2 r1c3");
+        assert!(board.filter_thermo(0));
+        assert_eq!(board.bounds((0,0)), Some((1,4)));
+        assert_eq!(board.bounds((0,2)), Some((6,9)));
+    }
+
+    #[test]
+    fn arrow_bounds_the_circle_and_its_cells() {
+        let mut board = board(&empty(), "arrow r1c1 r2c2 r3c3");
+        assert!(board.filter_arrow(0));
+        assert_eq!(board.bounds((0,0)), Some((2,9)));
+        assert_eq!(board.bounds((1,1)), Some((1,8)));
+        assert_eq!(board.bounds((2,2)), Some((1,8)));
+    }
+
+    #[test]
+    fn arrow_with_a_given_circle() {
+        let mut puzzle = empty();
+        puzzle.replace_range(0..1, "3");
+        let mut board = board(&puzzle, "arrow r1c1 r2c2 r3c3");
+        assert!(board.filter_arrow(0));
+        assert_eq!(board.options((1,1)), vec![1,2]);
+        assert_eq!(board.options((2,2)), vec![1,2]);
+    }
+}
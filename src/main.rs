@@ -2,9 +2,13 @@
 #![allow(dead_code)]
 #![allow(unused)]
 
-use core::panic;
-use std::{borrow::Borrow, cell::{Cell, RefCell}, collections::HashSet, fs::{self, File}, io::{Chain, Read}, ops::{Deref, Index}, path::PathBuf, usize};
+use std::path::PathBuf;
+use std::time::Duration;
 use clap::{ Parser, Subcommand};
+use sudoku_solver::{generator, multigrid, random, rating, steps, transform, GameBoard, Heuristic, SolveOptions, Status};
+use sudoku_solver::shape::{Shape, Variant};
+use sudoku_solver::sat::Engine;
+use sudoku_solver::steps::{cell_list, LogFormat, Technique};
 
 //-----------------------------------------------------------------------------
 // Structs and Enums
@@ -29,678 +33,239 @@ enum Commands {
 
     /// only runs a single pass then returns the found next step(s)
     Hint{
-        /// Read a puzzle from the command line as 81 numeric digits with '0' representing unknown values
-        #[arg(short = 's', long, value_name = "STRING")]
-        in_string: Option<String>,
-
-        /// Read a puzzle from a text file
-        #[arg(short = 'i', long, value_name = "FILE")]
-        in_file: Option<PathBuf>,
+        #[command(flatten)]
+        puzzle: PuzzleArgs,
 
         /// Verbose mode. Will write each step of the solve to the terminal
         #[arg(short, long)]
         verbose: bool,
+
+        /// Print the log of every placement and elimination as readable text or as JSON
+        #[arg(short = 'e', long, value_name = "FORMAT")]
+        explain: Option<LogFormat>,
     },
     
     /// solves the puzzle
     Solve {
-
-        /// Read a puzzle from the command line as 81 numeric digits with '0' representing unknown values
-        #[arg(short = 's', long, value_name = "STRING")]
-        in_string: Option<String>,
-
-        /// Read a puzzle from a text file
-        #[arg(short = 'i', long, value_name = "FILE")]
-        in_file: Option<PathBuf>,
+        #[command(flatten)]
+        puzzle: PuzzleArgs,
 
         /// Verbose mode. Will write each step of the solve to the terminal
         #[arg(short, long)]
         verbose: bool,
-    },
-}
 
+        /// Print the log of every placement and elimination as readable text or as JSON
+        #[arg(short = 'e', long, value_name = "FORMAT")]
+        explain: Option<LogFormat>,
 
-//-------------------------------------
-// Game Cells
-//   (are individual board elements)
-//-------------------------------------
-#[derive(Clone,Copy,Debug,PartialEq)]
-enum CellState {
-    Initial, // initial cells
-    Solved, //cells that have been solved in a previous itteration of the solver loop
-    New, //cells that have just been solved
-    Unsolved, //Default for unsolved cells
-    Guess, //used in recursion
-}
-#[derive(Clone,Debug)]
-struct GameCell {
-    pub value:usize,
-    pub possible_values:Vec<usize>,
-    pub state:CellState,
-}
-impl GameCell {
-    /// A new uninitialized cell set to a value of zero and 1..=9 possible values
-    fn new() -> GameCell {
-        GameCell{value:0,possible_values:vec![1,2,3,4,5,6,7,8,9],state:CellState::Unsolved}
-    }
+        /// Solver to use. sat encodes the puzzle as CNF for the built in CDCL solver, and with -v prints its statistics
+        /// and checks the solution is unique. It doesn't keep a step log
+        #[arg(long, value_enum, default_value = "search", conflicts_with = "explain")]
+        engine: Engine,
 
-    /// Removes a value, if present, from the possible value list
-    fn remove_possible_cell_value(&mut self, v:usize) {
-        if self.value == 0 {
-            self.possible_values.retain(|&x| x != v);
-        }
-    }
+        /// Seconds of guessing after which the search engine gives up and prints what it has so far
+        #[arg(long, value_name = "SECONDS")]
+        timeout: Option<f64>,
 
-    /// Keeps a particular possible value and removes all others
-    fn keep_only_possible_cell_value(&mut self, v:usize) {
-        if self.value == 0 {
-            self.possible_values.retain(|&x| x == v);
-        }
-    }
+        /// Most guesses the search engine will try before giving up
+        #[arg(long, value_name = "NUMBER")]
+        max_guesses: Option<usize>,
 
-    /// Keeps a particular possible value pair and removes all others
-    fn keep_only_possible_cell_value_pair(&mut self, v:(usize,usize)) {
-        if self.value == 0 {
-            self.possible_values.retain(|&x| x == v.0 || x == v.1);
-        }
-    }
-    
-    /// Sets the value of the cell and removes the list of possible values, unless setting to zero, in which case it will reset the possible values
-    fn set_value(&mut self, v:usize) {
-        self.value=v;
-        if v == 0 {
-            self.possible_values = vec![1,2,3,4,5,6,7,8,9];
-        } else {
-            self.possible_values.clear();
-        }
-    }
+        /// How the search engine picks its guesses: the first cell with the fewest possible values, the same with ties going
+        /// to the cell seeing the most unsolved cells, the digit with the fewest places left in a unit, the fewest values cell
+        /// trying the values that rule out the least first, or a random fewest values cell and value order
+        #[arg(long, value_enum, default_value = "smallest")]
+        heuristic: Heuristic,
 
-    /// Checks if there is only 1 possible value and updates the cells value accordingly.
-    fn check_possible(&mut self) -> bool {
-        if self.possible_values.len() == 1 {
-            self.set_value(self.possible_values[0]);
-            self.set_newly_solved();
-            return true;
-        }
-        false
-    }
+        /// Seed for the random heuristic
+        #[arg(long, value_name = "NUMBER", default_value_t = 0)]
+        seed: u64,
+    },
 
-    /// Prints the value of the cell
-    fn print(&self) {print!("{}",self.value);}
-
-    /// Prints the value of the cell using linux terminal escape sequences to color the output according to if the cell was an initial value of the puzzle, just solved, or previously solved
-    fn print_color(&self) {
-        match self.state {
-            CellState::Initial => print!("\x1b[91m\x1b[1m{}\x1b[0m",self.value),
-            CellState::Solved => print!("{}",self.value),
-            CellState::New => print!("\x1b[97m\x1b[1m{}\x1b[0m",self.value),
-            CellState::Unsolved => print!(" "),
-            CellState::Guess => print!("\x1b[95m\x1b[1m{}\x1b[0m",self.value),
-        }
-    }
+    /// rates the difficulty of the puzzle by solving it the way a person would
+    Rate {
+        #[command(flatten)]
+        puzzle: PuzzleArgs,
 
-    /// Prints cell in a 9 character wide format listing either the cell value or the possible cell values
-    fn print_detailed(&self) {
-        if self.value != 0 {
-            print!("    {}    ",self.value);
-        } else {
-            for i in 1..=9 {
-                if self.possible_values.contains(&i) {
-                    print!("{}",&i);
-                } else {
-                    print!(" ");
-                }
-            }
-        }
-    }
+        /// Print the human style solve step by step
+        #[arg(short, long)]
+        verbose: bool,
 
-    /// Initializes the cell's internal state (Used for cell colors in terminal output)
-    fn set_initial(&mut self) {if self.value == 0 {self.state = CellState::Unsolved;}else{self.state = CellState::Initial;}}
+        /// Print Sudoku Explainer compatible ER/EP/ED ratings instead of the score and tier
+        #[arg(long)]
+        se: bool,
+    },
 
-    /// Sets the cell to solved (Used for cell colors in terminal output)
-    fn set_newly_solved(&mut self) {self.state = CellState::New;}
+    /// generates a random puzzle with exactly one solution
+    Generate {
+        /// Seed for the random generator. The same seed always produces the same puzzle
+        #[arg(long, value_name = "NUMBER")]
+        seed: Option<u64>,
 
-    /// Sets the cell to previously solved (Used for cell colors in terminal output)
-    fn set_previously_solved(&mut self) {if self.state == CellState::New {self.state = CellState::Solved;} }
+        /// Box size as HEIGHTxWIDTH, e.g. 2x3 for a 6x6 board
+        #[arg(long = "box", value_name = "HxW", value_parser = Shape::parse)]
+        box_size: Option<Shape>,
 
-    fn set_guessed(&mut self) {self.state = CellState::Guess;}
-}
+        #[command(flatten)]
+        rules: RuleArgs,
 
-//-------------------------------------
-// Game Board
-//   Collection of Game Cells in 2d grid
-//-------------------------------------
-#[derive(Debug)]
-struct GameBoard {
-    pub board: Vec< Vec< RefCell<GameCell> > >,
-}
-impl GameBoard {
-
-    fn new() -> GameBoard {
-        let mut b = vec![
-            vec![RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new())],
-            vec![RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new())],
-            vec![RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new())],
-            vec![RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new())],
-            vec![RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new())],
-            vec![RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new())],
-            vec![RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new())],
-            vec![RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new())],
-            vec![RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new()),RefCell::new(GameCell::new())]
-        ];
-        GameBoard{
-            board:b
-        }
-    }
+        /// Symmetry of the clue layout
+        #[arg(long, value_enum, default_value = "none")]
+        symmetry: generator::Symmetry,
 
-    /// testing function to inject a board with a known solution
-    fn init_board_with_test_values(&mut self) {
-        self.init_board_from_string("091000203000002700705600000000713060009000000000500002000007304000060009000300015".to_string()); //solves in 8 steps with distribution
-    }
+        /// Exact layout of the clues with one character per cell, 'x' for a clue and '.' for an empty cell
+        #[arg(long, value_name = "PATTERN", conflicts_with = "symmetry")]
+        mask: Option<String>,
 
-    /// removes any cell possible values if that value already exists in it's row
-    fn set_possible_values_by_row(&mut self) { //will loop through rows to remove possible values
-        //check rows
-        for i in 0..9 {
-            for j in 0..9 { //for each cell in the row
-                let this_value = self.board[i][j].borrow().deref().value;
-                if this_value != 0 {
-                    for k in 0..9 {
-                        //remove value from row
-                        self.board[i][k].get_mut().remove_possible_cell_value(this_value);
-                    }
-                }
-            }
-        }
-    }
+        /// Keep generating until a puzzle is rated in this tier
+        #[arg(long, value_enum)]
+        difficulty: Option<rating::Tier>,
 
-    /// removes any cell possible values if that value already exists in it's column
-    fn set_possible_values_by_col(&mut self) { //will loop through columns to remove possible values
-        //check columns
-        for i in 0..9 {
-            for j in 0..9 { //for each cell in the col
-                let this_value = self.board[j][i].borrow().deref().value;
-                if this_value != 0 {
-                    for k in 0..9 {
-                        //remove value from col
-                        self.board[k][i].get_mut().remove_possible_cell_value(this_value);
-                    }
-                }
-            }
-        }
-    }
+        /// Keep generating until a puzzle needs this technique, named as in the JSON step log (e.g. xwing, naked_pair)
+        #[arg(long, value_name = "TECHNIQUE", value_parser = parse_technique)]
+        requires: Option<Technique>,
 
-    /// removes any cell possible values if that value already exists in its square
-    fn set_possible_values_by_square(&mut self) {
-        for sq_index      in [(0,0),(0,3),(0,6),(3,0),(3,3),(3,6),(6,0),(6,3),(6,6)]{ //2d vector offsets for the top left most square of each 3x3 cell
-            for sq_offset in [(0,0),(0,1),(0,2),(1,0),(1,1),(1,2),(2,0),(2,1),(2,2)]{ //2d offset values for each other square in the 3x3 cell
-                let this_value = self.board[sq_index.0+sq_offset.0][sq_index.1+sq_offset.1].borrow().deref().value;
-                for sq_offset_2 in [(0,0),(0,1),(0,2),(1,0),(1,1),(1,2),(2,0),(2,1),(2,2)]{
-                    self.board[sq_index.0+sq_offset_2.0][sq_index.1+sq_offset_2.1].get_mut().remove_possible_cell_value(this_value);
-                }
-            }
-        }
-    }
+        /// Seconds to spend searching for a puzzle that matches --difficulty or --requires
+        #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+        time_budget: u64,
 
-    /// prints the board without color
-    fn print_board(&self) {
-        for i in 0..9 {
-            for j in 0..9 {
-                self.board[i][j].borrow().print();
-                if j == 2 || j == 5 {
-                    print!("|");
-                }
-            }
-            println!();
-            if i == 2 || i == 5 {
-                println!{"---+---+---"};
-            }
-        }
-        println!();
-    }
+        /// Verbose mode. Will print the puzzle and its solution as boards
+        #[arg(short, long)]
+        verbose: bool,
+    },
 
-    /// print the board using linux color escapes. Red for Initial cells and white&Bold for newly solved cells
-    fn print_color_board(&self) {
-        for i in 0..9 {
-            for j in 0..9 {
-                self.board[i][j].borrow().print_color();
-                if j == 2 || j == 5 {
-                    print!("|");
-                }
-            }
-            println!();
-            if i == 2 || i == 5 {
-                println!{"---+---+---"};
-            }
-        }
-        println!();
-    }
+    /// removes givens until every remaining given is needed for a unique solution
+    Minimize {
+        #[command(flatten)]
+        puzzle: PuzzleArgs,
 
-    /// print all cells padded to 9 characters, if unsolved print the possible values remaining
-    fn print_detailed_board(&self) {
-        for i in 0..9 {
-            for j in 0..9 {
-                self.board[i][j].borrow().print_detailed();
-                print!(".");
-                if j == 2 || j == 5 {
-                    print!("|");
-                }
-            }
-            println!();
-            if i == 2 || i == 5 {
-                println!{"------------------------------+------------------------------+------------------------------"};
-            }
-        }
-        println!();
-    }
+        /// Verbose mode. Will list the removed givens and print the result as a board
+        #[arg(short, long)]
+        verbose: bool,
+    },
 
-    /// checks all unsolved cells to see if there is only one possible value remaining and updates the cell to that value and marks solved. Boolean return value indicates changes were made
-    fn set_values_from_possible(&mut self) -> bool {
-        let mut changes_made = false;
-        for i in 0..9 {
-            for j in 0..9 { //for each cell in the row
-                let mut tmp_bool = self.board[i][j].get_mut().check_possible();
-                if tmp_bool {
-                    changes_made = true;
-                }
-            }
-        }
-        changes_made
-    }
+    /// checks whether every given is needed for a unique solution and lists the ones that aren't
+    IsMinimal {
+        #[command(flatten)]
+        puzzle: PuzzleArgs,
+    },
 
-    /// Runs every loop of the algorithm to set the newly solved cells to just solved. Used for coloring newly solved cells differently
-    fn set_previously_solved_cells(&mut self) {
-        for i in 0..9 {
-            for j in 0..9 {
-                self.board[i][j].get_mut().set_previously_solved();
-            }
-        }
-    }
+    /// prints the canonical form of the puzzle, which is the same for every relabeling, swap, or transposition of it
+    Canonicalize {
+        #[command(flatten)]
+        classic: ClassicArgs,
+    },
 
-    /// Used to declare the current state of the board the initial state. All solved cells will be colored accordingly
-    fn set_initial_cells(&mut self) {
-        for i in 0..9 {
-            for j in 0..9 {
-                self.board[i][j].get_mut().set_initial();
-            }
-        }
-    }
+    /// applies a random or given chain of relabeling, swaps, rotations, and mirrors to the puzzle and prints the chain used
+    Transform {
+        #[command(flatten)]
+        classic: ClassicArgs,
 
-    /// takes an 81 character string and initialized the board with the appropriate values. 0 indicates unsolved
-    fn init_board_from_string(&mut self,in_str:String) {
-        let value_vector = convert_string_to_vector(&in_str);
-        for i in 0..9 {
-            for j in 0..9 {
-                self.board[i][j].get_mut().set_value( value_vector[ (i*9)+j ] );
-            }
-        }
-        self.set_initial_cells();
-    }
+        /// Chain of operations to apply, e.g. "digits=912345678 bands=201 rows0=102 rot=90". Random if not given
+        #[arg(long, value_name = "CHAIN")]
+        chain: Option<String>,
 
-    fn init_board_from_file(&mut self,in_file:PathBuf) {
-        let mut f = File::open( in_file );
-        if f.is_err() {
-            panic!("Error opening file");
-        }
-        let mut contents = String::new();
-        let mut o = f.unwrap();
-        o.read_to_string(&mut contents);
-        let mut t: String = contents.chars().filter(|c| c.is_numeric()).collect();
-        t.retain(|x|x != '\n');
-        t.retain(|x|x != '-');
-        t.retain(|x|x != '|');
-        t.retain(|x|x != '+');
-        t.retain(|x|x != ' ');
-        t.retain(|x|x != '*');
-        t.retain(|x|x != '_');
-        t.retain(|x|x != '.');
-        if t.len() == 81 {
-            self.init_board_from_string(t);
-        } else {
-            panic!("Invalid file contents:\n{}",t);
-        }
-    }
-    /// checks rows, columns, and squares to see if there are any possible values that appear only once
-    fn set_possible_values_by_distribution(&mut self) {
-        //rows
-        for i in 0..9 {
-            let mut distribution_vector: Vec<usize> = vec![0,0,0,0,0,0,0,0,0];
-            for j in 0..9 {
-                if self.board[i][j].borrow().deref().value == 0 {
-                    increment_values_by_index(&mut distribution_vector, 
-                        &self.board[i][j].borrow().possible_values)
-                }
-            }
-            //println!("{:?}",distribution_vector);
-            let mut k :usize = 1;//used to track what value things are
-            for x in distribution_vector.clone() {
-                if x == 1 {
-                    for j in 0..9 {
-                        if self.board[i][j].borrow().deref().possible_values.contains(&k) {
-                            self.board[i][j].get_mut().keep_only_possible_cell_value(k);
-                        }
-                    }
-                }
-                k += 1;
-            }
-        }
-        //columns
-        for i in 0..9 {
-            let mut distribution_vector: Vec<usize> = vec![0,0,0,0,0,0,0,0,0];
-            for j in 0..9 {
-                if self.board[j][i].borrow().deref().value == 0 {
-                    increment_values_by_index(&mut distribution_vector, 
-                        &self.board[j][i].borrow().possible_values)
-                }
-            }
-            //println!("{:?}",distribution_vector);
-            let mut k :usize = 1;//used to track what value things are
-            for x in distribution_vector.clone() {
-                if x == 1 {
-                    for j in 0..9 {
-                        if self.board[j][i].borrow().deref().possible_values.contains(&k) {
-                            self.board[j][i].get_mut().keep_only_possible_cell_value(k);
-                        }
-                    }
-                }
-                k += 1;
-            }
-        }
-        //squares
-        for sq_index      in [(0,0),(0,3),(0,6),(3,0),(3,3),(3,6),(6,0),(6,3),(6,6)]{ //2d vector offsets for the top left most square of each 3x3 cell
-            let mut distribution_vector: Vec<usize> = vec![0,0,0,0,0,0,0,0,0];
-            for sq_offset in [(0,0),(0,1),(0,2),(1,0),(1,1),(1,2),(2,0),(2,1),(2,2)]{ //2d offset values for each other square in the 3x3 cell
-                if self.board[sq_index.0+sq_offset.0][sq_index.1+sq_offset.1].borrow().deref().value == 0 {
-                    increment_values_by_index(&mut distribution_vector, 
-                        &self.board[sq_index.0+sq_offset.0][sq_index.1+sq_offset.1].borrow().possible_values)
-                }
-
-            }
-            //println!("{:?}",distribution_vector);
-            let mut k :usize = 1;//used to track what value things are
-            for x in distribution_vector.clone() {
-                if x == 1 {
-                    for sq_offset in [(0,0),(0,1),(0,2),(1,0),(1,1),(1,2),(2,0),(2,1),(2,2)]{
-                        if self.board[sq_index.0+sq_offset.0][sq_index.1+sq_offset.1].borrow().deref().possible_values.contains(&k) {
-                            self.board[sq_index.0+sq_offset.0][sq_index.1+sq_offset.1].get_mut().keep_only_possible_cell_value(k);
-                        }
-                    }
-                }
-                k += 1;
-            }
-        }
-    }
+        /// Apply the inverse of the chain, e.g. to map the solution of a transformed puzzle back to the original
+        #[arg(long, requires = "chain")]
+        invert: bool,
 
-    /// check the rows and columns in a square to see if the sum of known values and possible values == 3 and remove those values along the row/col and square
-    fn set_possible_values_by_short_segments(&mut self) {
-        //rows
-        for i in 0..9 {
-            for (j,range,not_range) in [(0,3..9,0..3),(3,0..3,3..6),(3,6..9,3..6),(6,0..6,6..9)]{
-                let mut segment: Vec<usize> = get_domain(
-                    self.board[i][j  ].borrow().deref(),
-                    self.board[i][j+1].borrow().deref(),
-                    self.board[i][j+2].borrow().deref() );
-                if segment.len() == 3 {
-                    for val in segment {
-                        for k in range.clone() {
-                            self.board[i][k].get_mut().remove_possible_cell_value(val);
-                        }
-                        //this logic is a bit ugly but what it does is check the other two parallel line segments in the parent square
-                        match i % 3 {
-                            0 => {
-                                for k in not_range.clone() {
-                                    self.board[i+1][k].get_mut().remove_possible_cell_value(val);
-                                    self.board[i+2][k].get_mut().remove_possible_cell_value(val);
-                                }
-                            }
-                            1 => {
-                                for k in not_range.clone() {
-                                    self.board[i-1][k].get_mut().remove_possible_cell_value(val);
-                                    self.board[i+1][k].get_mut().remove_possible_cell_value(val);
-                                }
-                            }
-                            2 => {
-                                for k in not_range.clone() {
-                                    self.board[i-2][k].get_mut().remove_possible_cell_value(val);
-                                    self.board[i-1][k].get_mut().remove_possible_cell_value(val);
-                                }
-                            }
-                            _ => panic!("math is hard for the compiler")
-                        }
-                    }
-                }
-            }
-        }
-        //cols
-        for i in 0..9 {
-            for (j,range,not_range) in [(0,3..9,0..3),(3,0..3,3..6),(3,6..9,3..6),(6,0..6,6..9)]{
-                let mut segment: Vec<usize> = get_domain(
-                    self.board[j  ][i].borrow().deref(),
-                    self.board[j+1][i].borrow().deref(),
-                    self.board[j+2][i].borrow().deref() );
-                if segment.len() == 3 {
-                    for val in segment {
-                        for k in range.clone() {
-                            self.board[k][i].get_mut().remove_possible_cell_value(val);
-                        }
-                        //this logic is a bit ugly but what it does is check the other two parallel line segments in the parent square
-                        match i % 3 {
-                            0 => {
-                                for k in not_range.clone() {
-                                    self.board[k][i+1].get_mut().remove_possible_cell_value(val);
-                                    self.board[k][i+2].get_mut().remove_possible_cell_value(val);
-                                }
-                            }
-                            1 => {
-                                for k in not_range.clone() {
-                                    self.board[k][i-1].get_mut().remove_possible_cell_value(val);
-                                    self.board[k][i+1].get_mut().remove_possible_cell_value(val);
-                                }
-                            }
-                            2 => {
-                                for k in not_range.clone() {
-                                    self.board[k][i-2].get_mut().remove_possible_cell_value(val);
-                                    self.board[k][i-1].get_mut().remove_possible_cell_value(val);
-                                }
-                            }
-                            _ => panic!("math is hard for the compiler")
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    /// function to check if there are no unsolved cells remaining
-    fn is_solved(&self)->bool{
-        for i in 0..9 {
-            for j in 0..9{
-                if self.board[i][j].borrow().deref().value == 0 {
-                    return false;
-                }
-            }
-        }
-        return true;
-    }
+        /// Seed for the random chain
+        #[arg(long, value_name = "NUMBER", conflicts_with = "chain")]
+        seed: Option<u64>,
+    },
 
-    /// runs through the constraint propagation algorithm once and returns the result
-    fn hint(&mut self) {
-        self.set_possible_values_by_row();
-        self.set_possible_values_by_col();
-        self.set_possible_values_by_square();
-        self.set_possible_values_by_distribution();
-        self.set_possible_values_by_short_segments();
-    
-        self.set_values_from_possible();
-        self.print_color_board();
-        println!();
-        self.print_detailed_board();
-    }
+    /// checks if two puzzles are the same puzzle up to relabeling, swaps, and transposition
+    Same {
+        /// The first puzzle, a classic 9x9 as 81 characters with '0' or '.' representing unknown values
+        first: String,
 
-    /// Will return the current game board as a 81 character string with '0' representing unsolved values.
-    fn board_to_string (&self)->String {
-        let mut returned :Vec<usize> = Vec::new();
-        returned.resize(81, 0);
-        for i in 0..9 {
-            for j in 0..9 {
-                returned[(i*9)+j] = self.board[i][j].borrow().deref().value
-            }
-        }
-        returned.into_iter().map(|i| i.to_string()).collect::<String>()
-    }
+        /// The second puzzle, in the same form as the first
+        second: String,
+    },
 
-    fn clone (&self)->GameBoard{
-        let mut returned = GameBoard::new();
-        returned.board = self.board.clone();
-        returned
-    }
+    /// writes the puzzle and its rules in a format for other solvers
+    Export {
+        #[command(flatten)]
+        puzzle: PuzzleArgs,
 
-    /// Will return the row and column of the first unsolved gamecell with the fewest possible values. will return none if used on a solved board.
-    fn get_smallest_possible_gamecell_by_idx (&self) -> Option<(usize,usize)> {
-        let mut smallest = 0;
-        for i in 0..9 {
-            for j in 0..9 {
-                if smallest !=0 {
-                    let tmp = self.board[i][j].borrow().deref().possible_values.len();
-                    if tmp != 0 && tmp < smallest {smallest=tmp;}
-                } else {
-                    smallest = self.board[i][j].borrow().deref().possible_values.len();
-                }
-                
-            }
-        }
-        if smallest == 0 {return None;}
-        for i in 0..9 {
-            for j in 0..9 {
-                if smallest == self.board[i][j].borrow().deref().possible_values.len() {
-                    return Some((i,j));
-                }
-                
-            }
-        }
-        return None;
-    }
+        /// Write the puzzle as CNF in the DIMACS format read by SAT solvers. The only format so far
+        #[arg(long, required = true)]
+        dimacs: bool,
+    },
 
-    /// check to see if there are any cells that are not assigned and have no potential values
-    fn is_unsolvable (&self) -> bool{
-        for i in 0..9 {
-            for j in 0..9 {
-                if self.board[i][j].borrow().possible_values.len() == 0 && self.board[i][j].borrow().deref().value == 0 {
-                    return true;
-                }
-            }
-        }
-        false
-    }
-    
-    /// primary solve loop. Will loopt through using constraint propogation until the board is solved or until there are no moves left. It will then create 
-    fn solve_loop(&mut self,verbose:bool) -> bool {
-        loop {
-            self.set_possible_values_by_row();
-            self.set_possible_values_by_col();
-            self.set_possible_values_by_square();
-            self.set_possible_values_by_distribution();
-            self.set_possible_values_by_short_segments();
-
-            let mut updated = self.set_values_from_possible();
-            if !updated && !self.is_unsolvable() {
-                // recursion logic
-
-                // debug stuff
-                //println!("{}",self.board_to_string());
-                
-                let target = self.get_smallest_possible_gamecell_by_idx().unwrap();
-                let target_size = self.board[target.0][target.1].borrow().possible_values.len();
-                
-                let mut possible_gameboards :Vec<GameBoard> = Vec::new();
-                possible_gameboards.resize_with(target_size, ||{self.clone()});
-                let possible_values_to_guess = self.board[target.0][target.1].borrow().possible_values.clone();
-                for i in 0..target_size {
-                    possible_gameboards[i].board[target.0][target.1].get_mut().set_value(possible_values_to_guess[i]);
-                    possible_gameboards[i].board[target.0][target.1].get_mut().set_guessed();
-                }
-                for i in 0..target_size {
-                    if possible_gameboards[i].solve_loop(false) {
-                        //update logic
-                        self.board[target.0][target.1].get_mut().set_value(possible_values_to_guess[i]);
-                        self.board[target.0][target.1].get_mut().set_guessed();
-                        updated = true;
-                        break;
-                    }
-                    if i == target_size - 1 && updated == false {return false;}
-                }
-                //return false;
-            }
-            if verbose && updated {
-                self.print_color_board();
-            }
-            self.set_previously_solved_cells();
-            if self.is_solved() {return true;}
-            if self.is_unsolvable() {return false;}
-        }
-    }
-    
-    /// Solve using the constraint propogation algorithm.
-    fn solve(&mut self,verbose:bool) {
-        if !self.solve_loop(verbose) {
-            self.print_detailed_board();
-        }
-        if !verbose {
-            self.print_board();
-        }
-    }
+    /// solves a samurai or another puzzle made of overlapping 9x9 grids
+    Samurai {
+        /// Read the puzzle from a text file with a picture of the whole board, spaces where there are no cells, and
+        /// optional "grid ROW COL" lines giving the top left cell of each grid. Without them the samurai layout is used
+        #[arg(short = 'i', long, value_name = "FILE")]
+        in_file: PathBuf,
 
+        /// Verbose mode. Will print the puzzle before solving and check the solution is unique
+        #[arg(short, long)]
+        verbose: bool,
+    },
 }
-
-
-//-----------------------------------------------------------------------------
-// Helper functions
-//     (All written by chatgpt)
-//-----------------------------------------------------------------------------
-fn convert_string_to_vector(input: &str) -> Vec<usize> {
-    if input.len() != 81 {
-        panic!("Input string must be exactly 81 characters long");
+/// The puzzle and its rules, shared by the commands that read one
+#[derive(clap::Args)]
+struct PuzzleArgs {
+    /// Read a puzzle from the command line with one character per cell and '0' or '.' representing unknown values
+    #[arg(short = 's', long, value_name = "STRING")]
+    in_string: Option<String>,
+
+    /// Read a puzzle from a text file
+    #[arg(short = 'i', long, value_name = "FILE")]
+    in_file: Option<PathBuf>,
+
+    /// Box size as HEIGHTxWIDTH, e.g. 2x3 for a 6x6 board. Worked out from the puzzle length if not given
+    #[arg(long = "box", value_name = "HxW", value_parser = Shape::parse)]
+    box_size: Option<Shape>,
+
+    #[command(flatten)]
+    rules: RuleArgs,
+
+    /// Constraint file with one killer cage, dot, sign, line, or outside clue per line, e.g. "cage 15 r1c1 r1c2 r2c1" or "renban r1c1 r1c2"
+    #[arg(long, value_name = "FILE")]
+    constraints: Option<PathBuf>,
+}
+impl PuzzleArgs {
+    /// builds the board with its shape, givens, and rules
+    fn load(self) -> GameBoard {
+        let mut board = match self.box_size {
+            Some(shape) => GameBoard::with_shape(shape),
+            None => GameBoard::new(),
+        };
+        board.init_board_from_args(self.in_string, self.in_file);
+        board.set_rules(self.rules.regions, &self.rules.variant, self.constraints);
+        board
     }
+}
 
-    let mut result = Vec::with_capacity(81);
+/// Jigsaw regions and variant rules, for the commands that read a puzzle and for generate
+#[derive(clap::Args)]
+struct RuleArgs {
+    /// Jigsaw region map with one character per cell, cells with the same character share a region
+    #[arg(long, value_name = "MAP")]
+    regions: Option<String>,
 
-    for c in input.chars() {
-        match c {
-            '0'..='9' => {
-                let digit = c.to_digit(10).unwrap() as usize;
-                result.push(digit);
-            }
-            _ => panic!("Invalid character found in input: {}", c),
-        }
-    }
-
-    result
+    /// Extra rules, comma separated: x for the diagonals, hyper for the windows between the boxes, anti-knight and anti-king for chess moves, disjoint for the same spot in every box
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "VARIANTS")]
+    variant: Vec<Variant>,
 }
 
-fn increment_values_by_index(base_vector: &mut Vec<usize>, indices_to_increment: & Vec<usize>) {
-    for &index in indices_to_increment {
-        let adjusted_index = index.checked_sub(1).unwrap_or_else(|| {
-            panic!("Index {} is out of bounds for vector of length 9", index);
-        });
+/// A classic 9x9 puzzle, for the commands that only work on those
+#[derive(clap::Args)]
+struct ClassicArgs {
+    /// Read a classic 9x9 puzzle from the command line as 81 characters with '0' or '.' representing unknown values
+    #[arg(short = 's', long, value_name = "STRING")]
+    in_string: Option<String>,
 
-        if adjusted_index < base_vector.len() {
-            base_vector[adjusted_index] += 1;
-        } else {
-            panic!("Index {} is out of bounds for vector of length 9", index);
-        }
-    }
+    /// Read a puzzle from a text file
+    #[arg(short = 'i', long, value_name = "FILE")]
+    in_file: Option<PathBuf>,
 }
 
-fn get_domain(cell1:&GameCell,cell2:&GameCell,cell3:&GameCell)->Vec<usize>{
-    let mut returnvec: Vec<usize> = Vec::new();
-    if cell1.value != 0 {returnvec.push(cell1.value);} else {returnvec.extend(cell1.possible_values.clone());}
-    if cell2.value != 0 {returnvec.push(cell2.value);} else {returnvec.extend(cell2.possible_values.clone());}
-    if cell3.value != 0 {returnvec.push(cell3.value);} else {returnvec.extend(cell3.possible_values.clone());}
-
-    // remove duplicates
-    let mut unique: HashSet<_> = returnvec.drain(..).collect();
-    let returnvec: Vec<usize> = unique.into_iter().collect();
-    return returnvec;
+fn parse_technique(id:&str) -> Result<Technique,String> {
+    Technique::from_id(id).ok_or_else(|| format!("unknown technique '{}'", id))
 }
 
 
@@ -740,35 +305,184 @@ fn main() {
             println!("After Algo:");
             sudoku_board.print_board();
         }
-        Commands::Hint { in_string, in_file, verbose } => {
-            if in_string.is_some() && in_file.is_some() {
-                panic!("you can only supply one puzzle to solve at a time");
-            } else if in_string.is_none() && in_file.is_none() {
-                panic!("you must supply a puzzle to solve");
-            } else if in_string.is_some() {
-                sudoku_board.init_board_from_string(in_string.unwrap());
-            } else if in_file.is_some() {
-                sudoku_board.init_board_from_file(in_file.unwrap());
+        Commands::Hint { puzzle, verbose, explain } => {
+            sudoku_board = puzzle.load();
+            if explain == Some(LogFormat::Json) {
+                let puzzle = sudoku_board.board_to_string();
+                sudoku_board.propagate();
+                println!("{}",steps::export_json(&puzzle, &sudoku_board.board_to_string(), sudoku_board.is_solved(), &sudoku_board.steps));
+            } else {
+                sudoku_board.hint();
             }
-            sudoku_board.set_initial_cells();
-            sudoku_board.hint();
 
         }
-        Commands::Solve { in_string, in_file, verbose } => {
-            
-            if in_string.is_some() && in_file.is_some() {
-                panic!("you can only supply one puzzle to solve at a time");
-            } else if in_string.is_none() && in_file.is_none() {
-                panic!("you must supply a puzzle to solve");
-            } else if in_string.is_some() {
-                sudoku_board.init_board_from_string(in_string.unwrap());
-            } else if in_file.is_some() {
-                sudoku_board.init_board_from_file(in_file.unwrap());
+        Commands::Solve { puzzle, verbose, explain, engine, timeout, max_guesses, heuristic, seed } => {
+            sudoku_board = puzzle.load();
+            if engine == Engine::Sat {
+                if !sudoku_board.solve_sat(verbose) {
+                    println!("Puzzle has no solution");
+                }
+                sudoku_board.print_board();
+                return;
+            }
+            let options = SolveOptions{
+                max_nodes: max_guesses,
+                max_time: timeout.map(Duration::from_secs_f64),
+                cancel_token: None,
+                heuristic,
+                seed,
+            };
+            match explain {
+                Some(LogFormat::Json) => {
+                    let puzzle = sudoku_board.board_to_string();
+                    let result = sudoku_board.solve_loop_with(false, &options);
+                    if let Status::GaveUp(limit) = result.status {
+                        eprintln!("Gave up, {} after {}", limit, result.stats);
+                    }
+                    let solved = result.status == Status::Solved;
+                    println!("{}",steps::export_json(&puzzle, &sudoku_board.board_to_string(), solved, &sudoku_board.steps));
+                }
+                Some(LogFormat::Text) => {
+                    sudoku_board.solve_with(verbose, &options);
+                    println!();
+                    steps::print_steps(&sudoku_board.steps);
+                }
+                None => {
+                    sudoku_board.solve_with(verbose, &options);
+                }
             }
-            sudoku_board.set_initial_cells();
-            sudoku_board.solve(verbose);
+        }
+        Commands::Rate { puzzle, verbose, se } => {
+            sudoku_board = puzzle.load();
+            let rating = sudoku_board.rate();
+            if verbose {
+                for step in rating.steps.iter().filter(|s| s.technique != Technique::Elimination) {
+                    println!("{}",step);
+                }
+                println!();
+            }
+            if se {
+                rating::SeRating::from_steps(&rating.steps).print();
+            } else {
+                rating.print();
+            }
+        }
+        Commands::Generate { seed, box_size, rules, symmetry, mask, difficulty, requires, time_budget, verbose } => {
+            let seed = seed.unwrap_or_else(|| {
+                let seed = random::Rng::seed_from_time();
+                eprintln!("seed: {}",seed);
+                seed
+            });
+            let mut rng = random::Rng::new(seed);
+            let mut shape = box_size.unwrap_or_default();
+            if let Some(regions) = rules.regions {
+                shape = shape.with_regions(&regions).unwrap_or_else(|e| panic!("{}",e));
+            }
+            shape = shape.with_variants(&rules.variant);
+            let mask = mask.map(|m| generator::parse_mask(&m, &shape));
+            let target = generator::Target{tier:difficulty, requires};
+            let puzzle = if target.is_set() {
+                let budget = Duration::from_secs(time_budget);
+                let (puzzle, report) = generator::generate_targeted(&mut rng, &shape, symmetry, mask.as_deref(), target, budget);
+                eprintln!("tried {} candidates in {:.1}s", report.candidates, report.elapsed.as_secs_f64());
+                match puzzle {
+                    Some(puzzle) => puzzle,
+                    None => panic!("no puzzle matching the target was found within the time budget"),
+                }
+            } else {
+                match mask {
+                    Some(mask) => match generator::generate_from_mask(&mut rng, &shape, &mask) {
+                        Some(puzzle) => puzzle,
+                        None => panic!("could not find a puzzle with a unique solution for that mask"),
+                    },
+                    None => match generator::generate(&mut rng, &shape, symmetry) {
+                        Some(puzzle) => puzzle,
+                        None => panic!("no puzzle can be made for that board, its rules leave no way to fill it"),
+                    },
+                }
+            };
+            println!("{}",puzzle.board_to_string());
+            if verbose {
+                println!();
+                puzzle.print_board();
+                let mut solution = puzzle.clone();
+                solution.solve_loop(false);
+                solution.print_board();
+            }
+        }
+        Commands::Minimize { puzzle, verbose } => {
+            sudoku_board = puzzle.load();
+            if !sudoku_board.has_unique_solution() {
+                panic!("the puzzle must have exactly one solution");
+            }
+            let removed = sudoku_board.minimize();
+            println!("{}",sudoku_board.board_to_string());
+            if verbose {
+                println!();
+                println!("Removed {} givens: {}",removed.len(),cell_list(&removed));
+                println!();
+                sudoku_board.print_board();
+            }
+        }
+        Commands::Canonicalize { classic } => {
+            sudoku_board.init_board_from_args(classic.in_string, classic.in_file);
+            println!("{}",sudoku_board.canonical_string());
+        }
+        Commands::Transform { classic, chain, invert, seed } => {
+            sudoku_board.init_board_from_args(classic.in_string, classic.in_file);
+            let chain = match chain {
+                Some(chain) => transform::parse_chain(&chain).unwrap_or_else(|e| panic!("{}",e)),
+                None => transform::random_chain(&mut random::Rng::new(seed.unwrap_or_else(random::Rng::seed_from_time))),
+            };
+            let mut mapping = transform::chain_transform(&chain);
+            if invert {
+                mapping = mapping.inverse();
+            }
+            println!("{}",sudoku_board.transformed(&mapping).board_to_string());
+            println!("chain: {}",transform::chain_to_string(&chain));
+        }
+        Commands::Same { first, second } => {
+            sudoku_board.init_board_from_string(first);
+            let mut other = GameBoard::new();
+            other.init_board_from_string(second);
+            if sudoku_board.is_isomorphic(&other) {
+                println!("same");
+            } else {
+                println!("different");
+            }
+        }
+        Commands::IsMinimal { puzzle } => {
+            sudoku_board = puzzle.load();
+            if !sudoku_board.has_unique_solution() {
+                panic!("the puzzle must have exactly one solution");
+            }
+            let redundant = sudoku_board.redundant_givens();
+            if redundant.is_empty() {
+                println!("minimal");
+            } else {
+                println!("not minimal, {} redundant givens: {}",redundant.len(),cell_list(&redundant));
+            }
+        }
+        Commands::Export { puzzle, dimacs } => {
+            sudoku_board = puzzle.load();
+            print!("{}", sudoku_board.to_dimacs());
+        }
+        Commands::Samurai { in_file, verbose } => {
+            let mut puzzle = multigrid::MultiGrid::from_file(&in_file).unwrap_or_else(|e| panic!("{}", e));
+            if verbose {
+                puzzle.print_board();
+                match puzzle.count_solutions(2) {
+                    0 => println!("no solutions"),
+                    1 => println!("unique solution"),
+                    _ => println!("more than one solution"),
+                }
+                println!();
+            }
+            if !puzzle.solve() {
+                println!("Puzzle has no solution");
+            }
+            puzzle.print_board();
         }
     }
 }
 
-
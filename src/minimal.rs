@@ -0,0 +1,44 @@
+//-----------------------------------------------------------------------------
+// Minimal Puzzles
+//   A puzzle is minimal when removing any one of its givens allows a second
+//   solution. Both checks only make sense for puzzles with one solution
+//-----------------------------------------------------------------------------
+
+use crate::GameBoard;
+
+impl GameBoard {
+    /// the cells of the givens that can be removed without losing the unique solution
+    pub fn redundant_givens(&self) -> Vec<(usize,usize)> {
+        let mut returned = Vec::new();
+        for (i,j) in self.given_cells() {
+            let mut board = self.search_clone();
+            board.board[i][j].get_mut().set_value(0);
+            if board.has_unique_solution() {
+                returned.push((i,j));
+            }
+        }
+        returned
+    }
+
+    /// removes redundant givens in board order until every remaining given is needed. Returns the removed cells.
+    /// One pass is enough since removing more givens can only make a given more necessary, never less
+    pub fn minimize(&mut self) -> Vec<(usize,usize)> {
+        let mut removed = Vec::new();
+        for (i,j) in self.given_cells() {
+            let value = self.board[i][j].borrow().value;
+            self.board[i][j].get_mut().set_value(0);
+            if self.has_unique_solution() {
+                removed.push((i,j));
+            } else {
+                self.board[i][j].get_mut().set_value(value);
+            }
+        }
+        self.set_initial_cells();
+        removed
+    }
+
+    /// every cell that holds a value, in board order
+    fn given_cells(&self) -> Vec<(usize,usize)> {
+        self.shape.cells().filter(|&(i,j)| self.board[i][j].borrow().value != 0).collect()
+    }
+}
@@ -0,0 +1,261 @@
+//-----------------------------------------------------------------------------
+// Multi-Grid Puzzles
+//   Several overlapping 9x9 grids, like the five grids of a samurai puzzle
+//   which share their corner boxes. Each grid is a GameBoard of its own and
+//   the cells they share are kept in step after every propagation pass, so
+//   a value placed or ruled out in one grid carries over to the others.
+//   Guessing uses the same explicit stack and undo trails as a single board,
+//   with a trail for each grid.
+//
+//   Puzzles are read from a picture of the whole board with one character
+//   per cell and spaces where there are no cells. Lines of the form
+//   "grid ROW COL" give the top left cell of each grid, one based, and
+//   without any the samurai layout is used
+//-----------------------------------------------------------------------------
+
+use std::path::Path;
+use crate::GameBoard;
+use crate::shape::Shape;
+
+/// The top left cells of the five grids of a samurai puzzle, zero based
+const SAMURAI: [(usize,usize); 5] = [(0,0), (0,12), (6,6), (12,0), (12,12)];
+
+/// A cell of the whole board, as the grid it's in and its row and column in that grid
+type GridCell = (usize,(usize,usize));
+
+pub struct MultiGrid {
+    pub grids: Vec<GameBoard>,
+    offsets: Vec<(usize,usize)>, // the top left cell of each grid on the whole board
+    shared: Vec<(GridCell,GridCell)>, // every pair of grid cells that are the same cell of the whole board
+}
+impl MultiGrid {
+    /// Reads the picture of a puzzle, with any "grid ROW COL" lines before it
+    pub fn parse(text:&str) -> Result<MultiGrid,String> {
+        let shape = Shape::classic();
+        let mut offsets = Vec::new();
+        let mut picture: Vec<Vec<char>> = Vec::new();
+        for line in text.lines() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.first() == Some(&"grid") {
+                match words[1..] {
+                    [row, col] => match (row.parse::<usize>(), col.parse::<usize>()) {
+                        (Ok(row), Ok(col)) if row > 0 && col > 0 => offsets.push((row-1, col-1)),
+                        _ => return Err(format!("invalid grid position '{}'", line)),
+                    },
+                    _ => return Err(format!("a grid line needs a row and a column, not '{}'", line)),
+                }
+            } else if !picture.is_empty() || !words.is_empty() {
+                picture.push(line.chars().collect());
+            }
+        }
+        if offsets.is_empty() {
+            offsets = SAMURAI.to_vec();
+        }
+        let at = |i:usize, j:usize| picture.get(i).and_then(|row| row.get(j)).copied().unwrap_or(' ');
+
+        let mut grids = Vec::new();
+        for &(top,left) in &offsets {
+            let mut grid = GameBoard::new();
+            for (i,j) in shape.cells() {
+                let c = at(top+i, left+j);
+                let value = shape.value_of(c).ok_or_else(|| format!("'{}' at row {} column {} is not a value", c, top+i+1, left+j+1))?;
+                grid.board[i][j].get_mut().set_value(value);
+            }
+            grid.set_initial_cells();
+            grid.record_steps = false;
+            grids.push(grid);
+        }
+        for (i,row) in picture.iter().enumerate() {
+            for (j,&c) in row.iter().enumerate() {
+                let inside = offsets.iter().any(|&(top,left)| (top..top+shape.size).contains(&i) && (left..left+shape.size).contains(&j));
+                if !c.is_whitespace() && !inside {
+                    return Err(format!("'{}' at row {} column {} is outside every grid", c, i+1, j+1));
+                }
+            }
+        }
+
+        let mut shared = Vec::new();
+        for (g,&(top,left)) in offsets.iter().enumerate() {
+            for (h,&(other_top,other_left)) in offsets.iter().enumerate().skip(g+1) {
+                for (i,j) in shape.cells() {
+                    let (row, col) = (top+i, left+j);
+                    if (other_top..other_top+shape.size).contains(&row) && (other_left..other_left+shape.size).contains(&col) {
+                        shared.push(((g,(i,j)), (h,(row-other_top, col-other_left))));
+                    }
+                }
+            }
+        }
+        let grid_count = offsets.len();
+        let returned = MultiGrid{grids, offsets, shared};
+        if grid_count > 1 && (0..grid_count).any(|g| !returned.shared.iter().any(|&((a,_),(b,_))| a == g || b == g)) {
+            return Err("every grid must overlap another one".to_string());
+        }
+        Ok(returned)
+    }
+
+    /// Reads a puzzle file
+    pub fn from_file(path:&Path) -> Result<MultiGrid,String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("error reading {}: {}", path.display(), e))?;
+        MultiGrid::parse(&text)
+    }
+
+    /// a copy for searching
+    fn search_clone(&self) -> MultiGrid {
+        MultiGrid{grids:self.grids.iter().map(|grid| grid.search_clone()).collect(), offsets:self.offsets.clone(), shared:self.shared.clone()}
+    }
+
+    /// copies values placed in one grid to the cells it shares with other grids, and keeps only the possible values
+    /// both copies of a shared cell agree on. Boolean return value indicates changes were made
+    fn sync_shared_cells(&mut self) -> bool {
+        let mut changed = false;
+        for &((g,(i,j)),(h,(k,l))) in &self.shared {
+            let a = self.grids[g].board[i][j].borrow().clone();
+            let b = self.grids[h].board[k][l].borrow().clone();
+            if a.value != 0 && b.value == 0 {
+                self.grids[h].cell_mut((k,l)).set_value(a.value);
+                self.grids[h].cell_mut((k,l)).state = a.state;
+                changed = true;
+            } else if b.value != 0 && a.value == 0 {
+                self.grids[g].cell_mut((i,j)).set_value(b.value);
+                self.grids[g].cell_mut((i,j)).state = b.state;
+                changed = true;
+            } else if a.value == 0 && b.value == 0 {
+                let both: Vec<usize> = a.possible_values.iter().copied().filter(|v| b.possible_values.contains(v)).collect();
+                if both.len() != a.possible_values.len() || both.len() != b.possible_values.len() {
+                    self.grids[g].cell_mut((i,j)).possible_values = both.clone();
+                    self.grids[h].cell_mut((k,l)).possible_values = both;
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// runs a propagation pass on every grid then brings the shared cells in step. Boolean return value indicates changes were made
+    fn propagate(&mut self) -> bool {
+        let mut changed = false;
+        for grid in &mut self.grids {
+            changed |= grid.propagate();
+        }
+        self.sync_shared_cells() || changed
+    }
+
+    fn is_solved(&self) -> bool {
+        self.grids.iter().all(|grid| grid.is_solved())
+    }
+
+    /// true if any grid can't be solved or a shared cell holds different values in two grids
+    fn is_unsolvable(&self) -> bool {
+        self.grids.iter().any(|grid| grid.is_unsolvable()) || self.shared.iter().any(|&((g,(i,j)),(h,(k,l)))| {
+            let (a, b) = (self.grids[g].board[i][j].borrow().value, self.grids[h].board[k][l].borrow().value);
+            a != 0 && b != 0 && a != b
+        })
+    }
+
+    /// the unsolved cell with the fewest possible values over every grid
+    fn smallest_unsolved(&self) -> Option<GridCell> {
+        let mut returned: Option<(usize,GridCell)> = None;
+        for (g,grid) in self.grids.iter().enumerate() {
+            for (i,j) in grid.shape.cells() {
+                let count = grid.board[i][j].borrow().possible_values.len();
+                if count != 0 && returned.is_none_or(|(smallest,_)| count < smallest) {
+                    returned = Some((count,(g,(i,j))));
+                }
+            }
+        }
+        returned.map(|(_,cell)| cell)
+    }
+
+    /// solves every grid together, guessing across the whole board when propagation gets stuck. Returns false if there is no solution
+    pub fn solve(&mut self) -> bool {
+        self.search(1) > 0
+    }
+
+    /// counts the solutions of the whole board. Stops counting once `limit` solutions are found
+    pub fn count_solutions(&self, limit:usize) -> usize {
+        self.search_clone().search(limit)
+    }
+
+    /// depth first search over guesses on the cell with the fewest possible values. Stops once `limit` solutions are
+    /// found and leaves the board on the last one, otherwise the board is put back how it started. Returns the number
+    /// of solutions found
+    fn search(&mut self, limit:usize) -> usize {
+        for grid in &mut self.grids {
+            grid.start_trail();
+        }
+        // each guess is the cell, the values left to try there, and the length of every grid's trail when it was made
+        let mut guesses: Vec<(GridCell, Vec<usize>, Vec<usize>)> = Vec::new();
+        let mut count = 0;
+        loop {
+            while self.propagate() {}
+            if !self.is_unsolvable() {
+                if self.is_solved() {
+                    count += 1;
+                    if count >= limit {break;}
+                } else if let Some((g,(i,j))) = self.smallest_unsolved() {
+                    let mut values = self.grids[g].board[i][j].borrow().possible_values.clone();
+                    values.reverse();
+                    let marks = self.grids.iter().map(|grid| grid.trail_mark()).collect();
+                    guesses.push(((g,(i,j)), values, marks));
+                }
+            }
+            // move on to the next value of the most recent guess, dropping guesses with none left
+            while guesses.last().is_some_and(|(_,values,_)| values.is_empty()) {
+                guesses.pop();
+            }
+            let Some(((g,(i,j)), values, marks)) = guesses.last_mut() else {
+                for grid in &mut self.grids {
+                    grid.undo_to(0);
+                }
+                break;
+            };
+            for (grid,&mark) in self.grids.iter_mut().zip(marks.iter()) {
+                grid.undo_to(mark);
+            }
+            let value = values.pop().unwrap();
+            self.grids[*g].cell_mut((*i,*j)).set_value(value);
+            self.grids[*g].cell_mut((*i,*j)).set_guessed();
+        }
+        for grid in &mut self.grids {
+            grid.end_trail();
+        }
+        count
+    }
+
+    /// the height and width of the whole board
+    fn extent(&self) -> (usize,usize) {
+        let height = self.offsets.iter().zip(&self.grids).map(|(&(top,_),grid)| top + grid.shape.size).max().unwrap_or(0);
+        let width = self.offsets.iter().zip(&self.grids).map(|(&(_,left),grid)| left + grid.shape.size).max().unwrap_or(0);
+        (height, width)
+    }
+
+    /// the whole board as rows of characters, with spaces where there are no cells
+    fn picture(&self) -> Vec<Vec<char>> {
+        let (height, width) = self.extent();
+        let mut returned = vec![vec![' '; width]; height];
+        for (grid,&(top,left)) in self.grids.iter().zip(&self.offsets) {
+            for (i,j) in grid.shape.cells() {
+                returned[top+i][left+j] = grid.shape.symbol(grid.board[i][j].borrow().value);
+            }
+        }
+        returned
+    }
+
+    /// prints the whole board with a gap between boxes. Grids are expected to line up with each other's boxes, as they do in a samurai
+    pub fn print_board(&self) {
+        let shape = Shape::classic();
+        for (i,row) in self.picture().into_iter().enumerate() {
+            let mut line = String::new();
+            for (j,c) in row.into_iter().enumerate() {
+                if j > 0 && j % shape.box_width == 0 {
+                    line.push(' ');
+                }
+                line.push(c);
+            }
+            println!("{}", line.trim_end());
+            if (i+1) % shape.box_height == 0 {
+                println!();
+            }
+        }
+    }
+}
@@ -0,0 +1,298 @@
+//-----------------------------------------------------------------------------
+// Outside Clues
+//   Clues written around the edge of the grid. A sandwich clue gives the sum
+//   of the values between the smallest and largest value in its row or
+//   column, and a little killer clue gives the sum of the diagonal its
+//   arrow points along. Values can repeat along a little killer diagonal
+//-----------------------------------------------------------------------------
+
+use std::rc::Rc;
+use crate::GameBoard;
+use crate::killer::has_matching;
+use crate::shape::{Shape, Unit};
+use crate::steps::{cell_list, cell_name, digit_list, Step, Technique};
+use crate::strategies::combinations;
+
+/// The sum between the smallest and largest values of a row or column
+#[derive(Clone,Debug)]
+pub struct Sandwich {
+    pub unit: Unit,
+    pub sum: usize,
+    combos: Vec<Vec<usize>>, // every set of values from 2..size that adds up to the sum, of any length
+}
+impl Sandwich {
+    pub fn new(unit:Unit, sum:usize, shape:&Shape) -> Result<Sandwich,String> {
+        let fillings = shape.size-2; // the values that can sit between the smallest and largest
+        let combos: Vec<Vec<usize>> = (0..=fillings)
+            .flat_map(|count| combinations(fillings, count))
+            .map(|combo| combo.into_iter().map(|k| k+2).collect::<Vec<usize>>())
+            .filter(|combo| combo.iter().sum::<usize>() == sum)
+            .collect();
+        if combos.is_empty() {
+            return Err(format!("no values between 1 and {} add up to {}", shape.size, sum));
+        }
+        Ok(Sandwich{unit,sum,combos})
+    }
+}
+
+/// The direction a little killer's arrow points, along one of the diagonals
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Direction {
+    DownRight,
+    DownLeft,
+    UpRight,
+    UpLeft,
+}
+impl Direction {
+    /// Reads a direction written as dr, dl, ur, or ul
+    pub fn parse(text:&str) -> Result<Direction,String> {
+        match text {
+            "dr" => Ok(Direction::DownRight),
+            "dl" => Ok(Direction::DownLeft),
+            "ur" => Ok(Direction::UpRight),
+            "ul" => Ok(Direction::UpLeft),
+            _ => Err(format!("'{}' is not a direction, use dr, dl, ur, or ul", text)),
+        }
+    }
+
+    /// The change in row and column for one step along the arrow
+    fn step(&self) -> (isize,isize) {
+        match self {
+            Direction::DownRight => (1,1),
+            Direction::DownLeft => (1,-1),
+            Direction::UpRight => (-1,1),
+            Direction::UpLeft => (-1,-1),
+        }
+    }
+
+    /// The arrow drawn next to the clue
+    fn symbol(&self) -> char {
+        match self {
+            Direction::DownRight | Direction::UpLeft => '\\',
+            Direction::DownLeft | Direction::UpRight => '/',
+        }
+    }
+}
+
+/// The sum of a diagonal, written outside the grid with an arrow pointing along it
+#[derive(Clone,Debug)]
+pub struct LittleKiller {
+    pub cells: Vec<(usize,usize)>,
+    pub sum: usize,
+    pub clue: (isize,isize), // where the clue is written, one step back from the first cell and so off the board
+    pub direction: Direction,
+}
+impl LittleKiller {
+    /// A little killer whose arrow starts at a cell on the edge of the board, running to the far edge
+    pub fn new(start:(usize,usize), direction:Direction, sum:usize, shape:&Shape) -> Result<LittleKiller,String> {
+        let (di,dj) = direction.step();
+        let on_board = |(i,j):(isize,isize)| (0..shape.size as isize).contains(&i) && (0..shape.size as isize).contains(&j);
+        let clue = (start.0 as isize - di, start.1 as isize - dj);
+        if on_board(clue) {
+            return Err(format!("the arrow at {} doesn't start from the edge of the board", cell_name(start)));
+        }
+        let mut cells = Vec::new();
+        let mut cell = (start.0 as isize, start.1 as isize);
+        while on_board(cell) {
+            cells.push((cell.0 as usize, cell.1 as usize));
+            cell = (cell.0 + di, cell.1 + dj);
+        }
+        if sum < cells.len() || sum > cells.len()*shape.size {
+            return Err(format!("{} cells can't add up to {}", cells.len(), sum));
+        }
+        Ok(LittleKiller{cells,sum,clue,direction})
+    }
+}
+
+impl GameBoard {
+    /// keeps only the possible values that fit some way of making every sandwich
+    pub fn set_possible_values_by_sandwiches(&mut self) {
+        for k in 0..self.constraints.sandwiches.len() {
+            self.filter_sandwich(k);
+        }
+    }
+
+    /// tries every place for the smallest and largest values of the line with every set of values between them that makes
+    /// the sum, keeping the values of each arrangement that still fits the line. Boolean return value indicates changes were made
+    pub fn filter_sandwich(&mut self, k:usize) -> bool {
+        let constraints = Rc::clone(&self.constraints);
+        let sandwich = &constraints.sandwiches[k];
+        let size = self.shape.size;
+        let cells = self.shape.unit_cells(sandwich.unit);
+        let options: Vec<Vec<usize>> = cells.iter().map(|&c| self.options(c)).collect();
+        let mut allowed: Vec<Vec<usize>> = vec![Vec::new(); cells.len()];
+        for low in (0..cells.len()).filter(|&p| options[p].contains(&1)) {
+            for high in (0..cells.len()).filter(|&p| p != low && options[p].contains(&size)) {
+                let between = low.min(high)+1..low.max(high);
+                for combo in sandwich.combos.iter().filter(|combo| combo.len() == between.len()) {
+                    let arrangement: Vec<Vec<usize>> = options.iter().enumerate().map(|(p,o)| {
+                        if p == low {vec![1]}
+                        else if p == high {vec![size]}
+                        else if between.contains(&p) {o.iter().copied().filter(|v| combo.contains(v)).collect()}
+                        else {o.iter().copied().filter(|v| *v != 1 && *v != size && !combo.contains(v)).collect()}
+                    }).collect();
+                    if !has_matching(&arrangement) {continue;}
+                    for (p,values) in arrangement.into_iter().enumerate() {
+                        for value in values {
+                            if !allowed[p].contains(&value) {allowed[p].push(value);}
+                        }
+                    }
+                }
+            }
+        }
+        let (open, allowed): (Vec<(usize,usize)>, Vec<Vec<usize>>) = cells.iter().copied().zip(allowed)
+            .filter(|&((i,j),_)| self.board[i][j].borrow().value == 0)
+            .unzip();
+        let (changed, removed) = self.restrict(&open, &allowed);
+        if changed.is_empty() {
+            return false;
+        }
+        if self.record_steps {
+            let reason = format!("the values between 1 and {} in {} add up to {}, so {} are removed from {}",
+                size, sandwich.unit, sandwich.sum, digit_list(&removed), cell_list(&changed));
+            self.steps.push(Step::new(Technique::Sandwich, changed, removed, reason));
+        }
+        true
+    }
+
+    /// keeps only the possible values along each little killer diagonal that let it make its sum
+    pub fn set_possible_values_by_little_killers(&mut self) {
+        for k in 0..self.constraints.little_killers.len() {
+            self.filter_little_killer(k);
+        }
+    }
+
+    /// keeps the values of a diagonal that some choice of values for the rest makes up the sum with. Values are allowed to
+    /// repeat, which is looser than the board but quick to check. Boolean return value indicates changes were made
+    pub fn filter_little_killer(&mut self, k:usize) -> bool {
+        let constraints = Rc::clone(&self.constraints);
+        let little_killer = &constraints.little_killers[k];
+        let options: Vec<Vec<usize>> = little_killer.cells.iter().map(|&c| self.options(c)).collect();
+        let allowed = repeating_sum_options(&options, little_killer.sum);
+        let (open, allowed): (Vec<(usize,usize)>, Vec<Vec<usize>>) = little_killer.cells.iter().copied().zip(allowed)
+            .filter(|&((i,j),_)| self.board[i][j].borrow().value == 0)
+            .unzip();
+        let (changed, removed) = self.restrict(&open, &allowed);
+        if changed.is_empty() {
+            return false;
+        }
+        if self.record_steps {
+            let reason = format!("the diagonal {} adds up to {}, so {} are removed from {}",
+                cell_list(&little_killer.cells), little_killer.sum, digit_list(&removed), cell_list(&changed));
+            self.steps.push(Step::new(Technique::LittleKiller, changed, removed, reason));
+        }
+        true
+    }
+
+    /// the clue written at a spot off the board, with the arrow of a little killer
+    pub fn outside_clue(&self, spot:(isize,isize)) -> Option<String> {
+        let little_killer = self.constraints.little_killers.iter().find(|lk| lk.clue == spot)
+            .map(|lk| format!("{}{}", lk.sum, lk.direction.symbol()));
+        let sandwich = self.constraints.sandwiches.iter().find(|sandwich| match sandwich.unit {
+            Unit::Row(i) => spot == (i as isize, -1),
+            Unit::Col(j) => spot == (-1, j as isize),
+            _ => false,
+        }).map(|sandwich| sandwich.sum.to_string());
+        little_killer.or(sandwich)
+    }
+
+    /// check to see if any sandwich or little killer can no longer make its sum
+    pub fn has_outside_conflicts(&self) -> bool {
+        let size = self.shape.size;
+        let value = |(i,j):(usize,usize)| self.board[i][j].borrow().value;
+        let sandwich_broken = self.constraints.sandwiches.iter().any(|sandwich| {
+            let values: Vec<usize> = self.shape.unit_cells(sandwich.unit).into_iter().map(value).collect();
+            match (values.iter().position(|&v| v == 1), values.iter().position(|&v| v == size)) {
+                (Some(low), Some(high)) => {
+                    let between = &values[low.min(high)+1..low.max(high)];
+                    let total: usize = between.iter().sum();
+                    total > sandwich.sum || (!between.contains(&0) && total != sandwich.sum)
+                }
+                _ => false,
+            }
+        });
+        let little_killer_broken = self.constraints.little_killers.iter().any(|lk| {
+            let total: usize = lk.cells.iter().map(|&c| value(c)).sum();
+            total > lk.sum || (lk.cells.iter().all(|&c| value(c) != 0) && total != lk.sum)
+        });
+        sandwich_broken || little_killer_broken
+    }
+}
+
+/// for each cell, the values from its choices that are part of some way for the cells to add up to the sum, allowing repeats.
+/// Works out which totals each run of cells from the start and from the end can reach, so long lines stay quick
+fn repeating_sum_options(choices:&[Vec<usize>], sum:usize) -> Vec<Vec<usize>> {
+    let n = choices.len();
+    // from_start[k][s] is true when the first k cells can add up to s, from_end[k][s] when the cells from k onwards can
+    let mut from_start = vec![vec![false; sum+1]; n+1];
+    let mut from_end = vec![vec![false; sum+1]; n+1];
+    from_start[0][0] = true;
+    from_end[n][0] = true;
+    for k in 0..n {
+        for s in 0..=sum {
+            from_start[k+1][s] = choices[k].iter().any(|&v| v <= s && from_start[k][s-v]);
+        }
+    }
+    for k in (0..n).rev() {
+        for s in 0..=sum {
+            from_end[k][s] = choices[k].iter().any(|&v| v <= s && from_end[k+1][s-v]);
+        }
+    }
+    choices.iter().enumerate().map(|(k,values)| values.iter().copied().filter(|&v| {
+        v <= sum && (0..=sum-v).any(|s| from_start[k][s] && from_end[k+1][sum-v-s])
+    }).collect()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::Constraints;
+
+    fn board(puzzle:&str, rules:&str) -> GameBoard {
+        let mut board = GameBoard::new();
+        board.init_board_from_string(puzzle.to_string());
+        board.constraints = Rc::new(Constraints::parse(rules, &board.shape).unwrap());
+        board
+    }
+
+    #[test]
+    fn repeating_sums() {
+        let any = vec![1,2,3,4,5,6,7,8,9];
+        assert_eq!(repeating_sum_options(&[any.clone(), any.clone(), any.clone()], 3), vec![vec![1]; 3]);
+        assert_eq!(repeating_sum_options(&[any.clone(), any.clone(), any.clone()], 27), vec![vec![9]; 3]);
+        assert_eq!(repeating_sum_options(&[vec![1,2], vec![5], vec![1,2]], 7), vec![vec![1], vec![5], vec![1]]);
+        assert_eq!(repeating_sum_options(&[vec![1,2], vec![5], vec![1,2]], 8), vec![vec![1,2], vec![5], vec![1,2]]);
+        assert_eq!(repeating_sum_options(&[vec![1,2], vec![5]], 9), vec![Vec::<usize>::new(); 2]);
+    }
+
+    #[test]
+    fn little_killer_allows_repeats() {
+        // r4c1, r3c2, r2c3, and r1c4 add up to 5, which needs three 1s and a 2
+        let mut board = board(&"0".repeat(81), "little-killer 5 r4c1 ur");
+        assert!(board.filter_little_killer(0));
+        for cell in [(3,0),(2,1),(1,2),(0,3)] {
+            assert_eq!(board.options(cell), vec![1,2]);
+        }
+    }
+
+    #[test]
+    fn sandwich_of_every_middle_value() {
+        // 2 to 8 add up to 35, so 1 and 9 sit at the ends of the row
+        let mut board = board(&"0".repeat(81), "sandwich row 1 35");
+        assert!(board.filter_sandwich(0));
+        assert_eq!(board.options((0,0)), vec![1,9]);
+        assert_eq!(board.options((0,8)), vec![1,9]);
+        for j in 1..8 {
+            assert_eq!(board.options((0,j)), vec![2,3,4,5,6,7,8]);
+        }
+    }
+
+    #[test]
+    fn sandwich_forces_a_placement() {
+        let mut puzzle = "0".repeat(81);
+        puzzle.replace_range(0..1, "1");
+        let mut board = board(&puzzle, "sandwich row 1 35");
+        assert!(board.filter_sandwich(0));
+        assert_eq!(board.options((0,8)), vec![9]);
+    }
+}
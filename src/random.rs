@@ -0,0 +1,48 @@
+//-----------------------------------------------------------------------------
+// Random Numbers
+//   Small seeded generator (xorshift64*) so a seed always produces the same
+//   puzzle no matter which platform or dependency versions are used
+//-----------------------------------------------------------------------------
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone,Debug)]
+pub struct Rng {
+    state: u64,
+}
+impl Rng {
+    /// A generator that always produces the same sequence for the same seed
+    pub fn new(seed:u64) -> Rng {
+        // scramble the seed so small seeds like 1, 2, 3 don't start out similar, and avoid the all zero state
+        let mut state = seed ^ 0x9E37_79B9_7F4A_7C15;
+        state = (state ^ (state >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        state ^= state >> 31;
+        Rng{state: if state == 0 {1} else {state}}
+    }
+
+    /// A seed taken from the system clock for when the user doesn't supply one
+    pub fn seed_from_time() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// A random number in 0..n
+    pub fn below(&mut self, n:usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Shuffles a slice in place (Fisher-Yates)
+    pub fn shuffle<T>(&mut self, items:&mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i+1);
+            items.swap(i,j);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//-----------------------------------------------------------------------------
+// Difficulty Rating
+//   Rates a puzzle by solving it with the human style strategies and looking
+//   at the hardest technique needed and how often each technique was used
+//-----------------------------------------------------------------------------
+
+use std::{collections::BTreeMap, fmt};
+use crate::GameBoard;
+use crate::steps::{Step, Technique, Unit};
+use crate::strategies::STRATEGY_ORDER;
+
+/// Difficulty tiers, easiest first
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord,clap::ValueEnum)]
+pub enum Tier {
+    Easy, // singles only
+    Medium, // needs pointing or claiming
+    Hard, // needs naked or hidden pairs and triples
+    Expert, // needs fish, wings, or quads
+    Extreme, // needs guessing
+}
+impl Tier {
+    fn promoted(&self) -> Tier {
+        match self {
+            Tier::Easy => Tier::Medium,
+            Tier::Medium => Tier::Hard,
+            Tier::Hard | Tier::Expert => Tier::Expert,
+            Tier::Extreme => Tier::Extreme,
+        }
+    }
+}
+impl fmt::Display for Tier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tier::Easy => write!(f,"easy"),
+            Tier::Medium => write!(f,"medium"),
+            Tier::Hard => write!(f,"hard"),
+            Tier::Expert => write!(f,"expert"),
+            Tier::Extreme => write!(f,"extreme"),
+        }
+    }
+}
+
+/// The number of uses of the hardest tier's techniques that pushes a puzzle up one tier
+const PROMOTION_COUNT: usize = 4;
+
+impl Technique {
+    /// How hard a technique is for a person to spot, in tenths of a point
+    pub fn weight(&self) -> usize {
+        match self {
+            Technique::Elimination => 0,
+            Technique::HiddenSingle => 12,
+            Technique::NakedSingle => 23,
+            Technique::Pointing => 26,
+            Technique::Claiming => 28,
+            Technique::ShortSegment => 28,
+            Technique::CageSum => 20,
+            Technique::InnieOutie => 29,
+            Technique::Kropki => 22,
+            Technique::Thermo => 18,
+            Technique::Arrow => 24,
+            Technique::XV => 20,
+            Technique::Sandwich => 30,
+            Technique::LittleKiller => 26,
+            Technique::Whisper => 20,
+            Technique::Renban => 24,
+            Technique::Palindrome => 16,
+            Technique::Parity => 10,
+            Technique::GreaterThan => 18,
+            Technique::Custom => 30,
+            Technique::NakedPair => 30,
+            Technique::XWing => 32,
+            Technique::HiddenPair => 34,
+            Technique::NakedTriple => 36,
+            Technique::Swordfish => 38,
+            Technique::HiddenTriple => 40,
+            Technique::XYWing => 42,
+            Technique::XYZWing => 44,
+            Technique::NakedQuad => 50,
+            Technique::Jellyfish => 52,
+            Technique::HiddenQuad => 54,
+            Technique::Guess => 100,
+        }
+    }
+
+    /// The tier a puzzle lands in when this is the hardest technique it needs
+    pub fn tier(&self) -> Tier {
+        match self {
+            Technique::Elimination | Technique::HiddenSingle | Technique::NakedSingle | Technique::Parity => Tier::Easy,
+            Technique::Pointing | Technique::Claiming | Technique::ShortSegment |
+            Technique::CageSum | Technique::InnieOutie | Technique::Kropki | Technique::Thermo | Technique::Arrow |
+            Technique::XV | Technique::Sandwich | Technique::LittleKiller |
+            Technique::Whisper | Technique::Renban | Technique::Palindrome | Technique::GreaterThan | Technique::Custom => Tier::Medium,
+            Technique::NakedPair | Technique::HiddenPair | Technique::NakedTriple | Technique::HiddenTriple => Tier::Hard,
+            Technique::XWing | Technique::Swordfish | Technique::Jellyfish |
+            Technique::XYWing | Technique::XYZWing | Technique::NakedQuad | Technique::HiddenQuad => Tier::Expert,
+            Technique::Guess => Tier::Extreme,
+        }
+    }
+}
+
+/// The result of rating a puzzle
+#[derive(Clone,Debug)]
+pub struct Rating {
+    pub score: usize,
+    pub tier: Tier,
+    pub hardest: Technique,
+    pub solved: bool,
+    pub histogram: BTreeMap<Technique,usize>, // number of steps per technique, eliminations are not counted
+    pub steps: Vec<Step>, // the human style solve that was rated
+}
+impl Rating {
+    /// Builds a rating from the step log of a human style solve
+    pub fn from_steps(steps:Vec<Step>, solved:bool) -> Rating {
+        let mut histogram: BTreeMap<Technique,usize> = BTreeMap::new();
+        for step in steps.iter().filter(|s| s.technique != Technique::Elimination) {
+            *histogram.entry(step.technique).or_insert(0) += 1;
+        }
+        let hardest = histogram.keys().copied().max_by_key(|t| t.weight()).unwrap_or(Technique::Elimination);
+
+        // the hardest technique sets the base of the score and every step harder than a single adds to it
+        let usage: usize = histogram.iter()
+            .filter(|(t,_)| t.tier() > Tier::Easy)
+            .map(|(t,count)| t.weight()*count)
+            .sum();
+        let score = hardest.weight()*10 + usage/10;
+
+        let mut tier = hardest.tier();
+        let uses_at_tier: usize = histogram.iter().filter(|(t,_)| t.tier() == tier).map(|(_,count)| count).sum();
+        if tier > Tier::Easy && uses_at_tier >= PROMOTION_COUNT {
+            tier = tier.promoted();
+        }
+
+        Rating{score,tier,hardest,solved,histogram,steps}
+    }
+
+    /// Prints the score, tier, and a histogram of the techniques used
+    pub fn print(&self) {
+        if !self.solved {
+            println!("Puzzle has no solution");
+        }
+        println!("Score: {}",self.score);
+        println!("Tier: {}",self.tier);
+        println!("Hardest technique: {}",self.hardest);
+        println!();
+        let widest = self.histogram.values().copied().max().unwrap_or(0);
+        for (technique,count) in &self.histogram {
+            // scale the bars so the longest one is at most 50 characters
+            let bar = (count*50).div_ceil(widest.max(50));
+            println!("{:<14} {:>3} {}",technique.name(),count,"#".repeat(bar.max(1)));
+        }
+    }
+}
+
+//-------------------------------------
+// Sudoku Explainer Ratings
+//   ER is the hardest step of the solve, EP the hardest step up to the first
+//   placement, and ED the first step. Values use Sudoku Explainer's weights
+//-------------------------------------
+
+/// A single Sudoku Explainer rating in tenths of a point. Not exact when the solve needed a
+/// guess, since Sudoku Explainer would have used a chaining technique this solver doesn't have.
+/// Inexact values are printed with a trailing '+' meaning harder than the value shown
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct SeValue {
+    pub tenths: usize,
+    pub exact: bool,
+}
+impl fmt::Display for SeValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,"{}.{}{}",self.tenths/10,self.tenths%10,if self.exact {""} else {"+"})
+    }
+}
+
+/// The ER/EP/ED ratings of a puzzle
+#[derive(Clone,Copy,Debug)]
+pub struct SeRating {
+    pub er: SeValue,
+    pub ep: SeValue,
+    pub ed: SeValue,
+}
+impl SeRating {
+    /// Builds the ratings from the step log of a human style solve
+    pub fn from_steps(steps:&[Step]) -> SeRating {
+        let rated: Vec<(&Step,Option<usize>)> = steps.iter()
+            .filter(|s| s.technique != Technique::Elimination)
+            .map(|s| (s,se_weight(s)))
+            .collect();
+        let first_placement = rated.iter().position(|(s,_)| s.technique.is_placement()).unwrap_or(rated.len().max(1)-1);
+        SeRating{
+            er: hardest(&rated),
+            ep: hardest(&rated[..rated.len().min(first_placement+1)]),
+            ed: hardest(&rated[..rated.len().min(1)]),
+        }
+    }
+
+    /// Prints the ratings in the ER/EP/ED notation used by public puzzle collections
+    pub fn print(&self) {
+        println!("ER/EP/ED = {}/{}/{}",self.er,self.ep,self.ed);
+    }
+}
+
+/// The Sudoku Explainer weight of a step, None for guesses which Sudoku Explainer doesn't make
+fn se_weight(step:&Step) -> Option<usize> {
+    match (step.technique, step.unit) {
+        (Technique::Guess, _) => None,
+        (Technique::HiddenSingle, Some(Unit::Row(_)) | Some(Unit::Col(_))) => Some(15),
+        (technique, _) => Some(technique.weight()),
+    }
+}
+
+fn hardest(rated:&[(&Step,Option<usize>)]) -> SeValue {
+    let mut tenths = rated.iter().filter_map(|(_,w)| *w).max().unwrap_or(0);
+    let exact = rated.iter().all(|(_,w)| w.is_some());
+    if !exact {
+        // a guess is only made once every technique has failed, so the step is harder than all of them
+        tenths = tenths.max(STRATEGY_ORDER.iter().map(|t| t.weight()).max().unwrap_or(0));
+    }
+    SeValue{tenths,exact}
+}
+
+impl GameBoard {
+    /// Solves a copy of the board with the human style strategies and rates the steps it took
+    pub fn rate(&self) -> Rating {
+        let mut board = self.clone();
+        let solved = board.solve_human();
+        Rating::from_steps(board.steps, solved)
+    }
+}
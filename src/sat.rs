@@ -0,0 +1,418 @@
+//-----------------------------------------------------------------------------
+// SAT Encoding
+//   Writes a puzzle, with its variants and constraint file, as a formula in
+//   conjunctive normal form. Variable ((r*size + c)*size + v) is true when
+//   the zero based cell r,c holds the value v, and extra variables stand for
+//   the running totals of sums. The formula can be exported in the DIMACS
+//   format read by other SAT solvers, or solved by the CDCL solver in cdcl.rs
+//   with the model written back into the board
+//-----------------------------------------------------------------------------
+
+use crate::GameBoard;
+use crate::cdcl::{Solver, Stats};
+use crate::lines::{whisper_gap, LineKind};
+
+/// The solver used by the solve command
+#[derive(Clone,Copy,Debug,PartialEq,Eq,clap::ValueEnum)]
+pub enum Engine {
+    Search, // constraint propagation with guessing
+    Sat, // the CNF encoding and the CDCL solver
+}
+
+/// What a run of the CDCL solver found, with the size of the formula and the solver's work
+struct SatRun {
+    solved: bool,
+    unique: Option<bool>, // only checked when asked for
+    variables: usize,
+    clauses: usize,
+    stats: Stats, // the work done finding the solution, not checking it is unique
+}
+
+/// A formula in conjunctive normal form using DIMACS literals, 1 based variables negated for false
+pub struct Cnf {
+    pub variables: usize,
+    pub clauses: Vec<Vec<i32>>,
+    size: usize,
+}
+impl Cnf {
+    fn new(size:usize) -> Cnf {
+        Cnf{variables:size*size*size, clauses:Vec::new(), size}
+    }
+
+    /// the variable that is true when a cell holds a value
+    fn cell(&self, (i,j):(usize,usize), value:usize) -> i32 {
+        ((i*self.size + j)*self.size + value) as i32
+    }
+
+    fn new_variable(&mut self) -> i32 {
+        self.variables += 1;
+        self.variables as i32
+    }
+
+    /// no two of the cells hold the same value
+    fn all_different(&mut self, cells:&[(usize,usize)]) {
+        for (k,&a) in cells.iter().enumerate() {
+            for &b in &cells[k+1..] {
+                self.forbid_pairs(a, b, |x,y| x != y);
+            }
+        }
+    }
+
+    /// rules out every pair of values for two cells that the rule doesn't allow
+    fn forbid_pairs(&mut self, a:(usize,usize), b:(usize,usize), allows:impl Fn(usize,usize)->bool) {
+        for x in 1..=self.size {
+            for y in (1..=self.size).filter(|&y| !allows(x, y)) {
+                self.clauses.push(vec![-self.cell(a, x), -self.cell(b, y)]);
+            }
+        }
+    }
+
+    /// variables for the running total of the cells, indexed by the total. The variable for the true total of all
+    /// the cells is forced to be true, so ruling out a total's variable rules out the values that make it
+    fn totals(&mut self, cells:&[(usize,usize)]) -> Vec<Option<i32>> {
+        let mut layer: Vec<Option<i32>> = (0..=self.size).map(|v| (v > 0).then(|| self.cell(cells[0], v))).collect();
+        for &c in &cells[1..] {
+            let mut next: Vec<Option<i32>> = vec![None; layer.len() + self.size];
+            for (total,&before) in layer.iter().enumerate() {
+                let Some(before) = before else {continue};
+                for v in 1..=self.size {
+                    let after = match next[total+v] {
+                        Some(after) => after,
+                        None => *next[total+v].insert(self.new_variable()),
+                    };
+                    self.clauses.push(vec![-before, -self.cell(c, v), after]);
+                }
+            }
+            layer = next;
+        }
+        layer
+    }
+
+    /// a new variable that is true exactly when any of the literals is
+    fn any(&mut self, lits:&[i32]) -> i32 {
+        let returned = self.new_variable();
+        self.clauses.push(std::iter::once(-returned).chain(lits.iter().copied()).collect());
+        for &l in lits {
+            self.clauses.push(vec![returned, -l]);
+        }
+        returned
+    }
+
+    /// a new variable that is true exactly when all of the literals are
+    fn all(&mut self, lits:&[i32]) -> i32 {
+        let returned = self.new_variable();
+        self.clauses.push(std::iter::once(returned).chain(lits.iter().map(|l| -l)).collect());
+        for &l in lits {
+            self.clauses.push(vec![-returned, l]);
+        }
+        returned
+    }
+
+    /// the values strictly between the smallest and largest values of the cells add up to the sum. Each cell gets a
+    /// variable for being one of the two crusts, and for being inside them once one crust is behind it and not both
+    fn sandwich(&mut self, cells:&[(usize,usize)], sum:usize) {
+        let truth = self.new_variable();
+        self.clauses.push(vec![truth]);
+        let mut seen: Option<i32> = None; // one crust or more is before the cell, None while that can't be
+        let mut done: Option<i32> = None; // both crusts are before the cell
+        let mut layer: Vec<Option<i32>> = vec![Some(truth)]; // the running total of the cells inside, indexed by the total
+        for &c in cells {
+            let crust = self.any(&[self.cell(c, 1), self.cell(c, self.size)]);
+            if let Some(seen) = seen {
+                let inside = match done {
+                    Some(done) => self.all(&[seen, -done, -crust]),
+                    None => self.all(&[seen, -crust]),
+                };
+                let mut next: Vec<Option<i32>> = vec![None; sum+1];
+                for (total,&before) in layer.iter().enumerate() {
+                    let Some(before) = before else {continue};
+                    let after = *next[total].get_or_insert_with(|| self.new_variable());
+                    self.clauses.push(vec![-before, inside, after]);
+                    for v in 1..=self.size {
+                        let mut clause = vec![-before, -inside, -self.cell(c, v)];
+                        if total + v <= sum {
+                            clause.push(*next[total+v].get_or_insert_with(|| self.new_variable()));
+                        }
+                        self.clauses.push(clause);
+                    }
+                }
+                layer = next;
+                let both = self.all(&[seen, crust]);
+                done = Some(match done {
+                    Some(done) => self.any(&[done, both]),
+                    None => both,
+                });
+            }
+            seen = Some(match seen {
+                Some(seen) => self.any(&[seen, crust]),
+                None => crust,
+            });
+        }
+        for (total,variable) in layer.into_iter().enumerate() {
+            if let Some(variable) = variable.filter(|_| total != sum) {
+                self.clauses.push(vec![-variable]);
+            }
+        }
+    }
+
+    /// the cells add up to the sum
+    fn sum(&mut self, cells:&[(usize,usize)], sum:usize) {
+        for (total,variable) in self.totals(cells).into_iter().enumerate() {
+            if let Some(variable) = variable.filter(|_| total != sum) {
+                self.clauses.push(vec![-variable]);
+            }
+        }
+    }
+
+    /// Writes the formula in the DIMACS format, with the comment lines first
+    pub fn to_dimacs(&self, comments:&[String]) -> String {
+        let mut returned = String::new();
+        for comment in comments {
+            returned.push_str(&format!("c {}\n", comment));
+        }
+        returned.push_str(&format!("p cnf {} {}\n", self.variables, self.clauses.len()));
+        for clause in &self.clauses {
+            for l in clause {
+                returned.push_str(&format!("{} ", l));
+            }
+            returned.push_str("0\n");
+        }
+        returned
+    }
+}
+
+impl GameBoard {
+    /// Encodes the board, its variants, and its constraints as CNF. Custom rules can't be encoded
+    pub fn to_cnf(&self) -> Result<Cnf,String> {
+        let constraints = &self.constraints;
+        if !constraints.custom.is_empty() {
+            return Err("custom rules can't be written as CNF".to_string());
+        }
+        let size = self.shape.size;
+        let mut cnf = Cnf::new(size);
+
+        // every cell holds a value and every unit holds each value, which with the unit's cells being
+        // different means once
+        for cell in self.shape.cells() {
+            cnf.clauses.push((1..=size).map(|v| cnf.cell(cell, v)).collect());
+            for x in 1..=size {
+                for y in x+1..=size {
+                    cnf.clauses.push(vec![-cnf.cell(cell, x), -cnf.cell(cell, y)]);
+                }
+            }
+            let value = self.board[cell.0][cell.1].borrow().value;
+            if value != 0 {
+                cnf.clauses.push(vec![cnf.cell(cell, value)]);
+            }
+        }
+        for unit in self.shape.units() {
+            let cells = self.shape.unit_cells(unit);
+            for v in 1..=size {
+                cnf.clauses.push(cells.iter().map(|&c| cnf.cell(c, v)).collect());
+            }
+            cnf.all_different(&cells);
+        }
+        for &variant in &self.shape.variants {
+            for a in self.shape.cells() {
+                for b in self.shape.move_peers(a, variant).into_iter().filter(|&b| b > a) {
+                    cnf.forbid_pairs(a, b, |x,y| x != y);
+                }
+            }
+        }
+
+        for cage in &constraints.cages {
+            cnf.all_different(&cage.cells);
+            cnf.sum(&cage.cells, cage.sum);
+        }
+        for ([a,b], rule) in self.pair_rules() {
+            cnf.forbid_pairs(a, b, |x,y| rule.allows(x, y));
+        }
+        for thermo in &constraints.thermos {
+            for pair in thermo.cells.windows(2) {
+                cnf.forbid_pairs(pair[0], pair[1], |x,y| x < y);
+            }
+        }
+        for arrow in &constraints.arrows {
+            for (total,variable) in cnf.totals(&arrow.cells).into_iter().enumerate() {
+                let Some(variable) = variable else {continue};
+                let clause = if (1..=size).contains(&total) {vec![-variable, cnf.cell(arrow.circle, total)]} else {vec![-variable]};
+                cnf.clauses.push(clause);
+            }
+        }
+        for line in &constraints.lines {
+            let n = line.cells.len();
+            match line.kind {
+                LineKind::Whisper => for pair in line.cells.windows(2) {
+                    cnf.forbid_pairs(pair[0], pair[1], |x,y| x.abs_diff(y) >= whisper_gap(size));
+                },
+                // different values no more than n-1 apart are a run of n consecutive values
+                LineKind::Renban => for (k,&a) in line.cells.iter().enumerate() {
+                    for &b in &line.cells[k+1..] {
+                        cnf.forbid_pairs(a, b, |x,y| x != y && x.abs_diff(y) < n);
+                    }
+                },
+                LineKind::Palindrome => for k in 0..n/2 {
+                    cnf.forbid_pairs(line.cells[k], line.cells[n-1-k], |x,y| x == y);
+                },
+            }
+        }
+        for sandwich in &constraints.sandwiches {
+            cnf.sandwich(&self.shape.unit_cells(sandwich.unit), sandwich.sum);
+        }
+        for little_killer in &constraints.little_killers {
+            cnf.sum(&little_killer.cells, little_killer.sum);
+        }
+        for &(cell, parity) in &constraints.parities {
+            for v in (1..=size).filter(|&v| !parity.allows(v)) {
+                cnf.clauses.push(vec![-cnf.cell(cell, v)]);
+            }
+        }
+        for sign in &constraints.signs {
+            cnf.forbid_pairs(sign.cells[0], sign.cells[1], |x,y| x > y);
+        }
+        Ok(cnf)
+    }
+
+    /// Writes the board as DIMACS CNF, with comments giving the puzzle and how to read the variables
+    pub fn to_dimacs(&self) -> String {
+        let cnf = self.to_cnf().unwrap_or_else(|e| panic!("{}", e));
+        let size = self.shape.size;
+        let comments = [
+            format!("sudoku {} puzzle {}", self.shape, self.board_to_string()),
+            format!("variable ((row-1)*{} + col-1)*{} + value is true when r<row>c<col> holds value, variables above {} are running totals", size, size, size*size*size),
+        ];
+        cnf.to_dimacs(&comments)
+    }
+
+    /// Solves the board with the CDCL solver and fills in the solution. With verbose the size of the formula and the
+    /// solver's work are printed, along with whether the solution is unique. Returns false if there is no solution
+    pub fn solve_sat(&mut self, verbose:bool) -> bool {
+        let run = self.run_sat(verbose);
+        if verbose {
+            let stats = run.stats;
+            println!("{} variables, {} clauses", run.variables, run.clauses);
+            println!("{} decisions, {} propagations, {} conflicts, {} restarts", stats.decisions, stats.propagations, stats.conflicts, stats.restarts);
+            if let Some(unique) = run.unique {
+                println!("{}", if unique {"unique solution"} else {"more than one solution"});
+            }
+            println!();
+        }
+        run.solved
+    }
+
+    /// solves with the CDCL solver and fills in the solution. With check_unique the solution is then ruled out and
+    /// the solver asked for a different one
+    fn run_sat(&mut self, check_unique:bool) -> SatRun {
+        let cnf = self.to_cnf().unwrap_or_else(|e| panic!("{}", e));
+        let mut solver = Solver::new(cnf.variables);
+        for clause in &cnf.clauses {
+            solver.add_clause(clause);
+        }
+        let model = solver.solve();
+        if let Some(model) = &model {
+            for (i,j) in self.shape.cells() {
+                let cell = self.board[i][j].get_mut();
+                if cell.value == 0 {
+                    let value = (1..=cnf.size).find(|&v| model[cnf.cell((i,j), v) as usize]).unwrap();
+                    cell.set_value(value);
+                    cell.set_newly_solved();
+                }
+            }
+        }
+        let stats = solver.stats;
+        let mut unique = None;
+        if check_unique && model.is_some() {
+            let blocking: Vec<i32> = self.shape.cells().map(|(i,j)| -cnf.cell((i,j), self.board[i][j].borrow().value)).collect();
+            solver.add_clause(&blocking);
+            unique = Some(solver.solve().is_none());
+        }
+        SatRun{solved:model.is_some(), unique, variables:cnf.variables, clauses:cnf.clauses.len(), stats}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use crate::constraints::Constraints;
+    use crate::generator::{generate, Symmetry};
+    use crate::random::Rng;
+    use crate::shape::{Shape, Variant};
+
+    const PUZZLE: &str = "002000063009000001006000400020180070900760000070490816000800007300040008008000940";
+
+    fn board(puzzle:&str, rules:&str) -> GameBoard {
+        let mut board = GameBoard::new();
+        board.init_board_from_string(puzzle.to_string());
+        board.constraints = Rc::new(Constraints::parse(rules, &board.shape).unwrap());
+        board
+    }
+
+    /// solves copies of a puzzle with a unique solution with the CDCL solver and the search, which have to agree
+    fn check_against_search(puzzle:&GameBoard) {
+        let mut sat = puzzle.clone();
+        assert_eq!(sat.run_sat(true).unique, Some(true));
+        let mut search = puzzle.clone();
+        assert!(search.solve_loop(false));
+        assert_eq!(sat.board_to_string(), search.board_to_string());
+    }
+
+    #[test]
+    fn constraint_files_match_the_search() {
+        let empty = "0".repeat(81);
+        for rules in [
+            include_str!("../examples/killer.txt"),
+            include_str!("../examples/kropki.txt"),
+            include_str!("../examples/thermo.txt"),
+            include_str!("../examples/sandwich.txt"),
+            include_str!("../examples/greater.txt"),
+        ] {
+            check_against_search(&board(&empty, rules));
+        }
+        let lines = "000000000000000000000000002000009000000000000000000000000000104000300000016200000";
+        check_against_search(&board(lines, include_str!("../examples/lines.txt")));
+    }
+
+    #[test]
+    fn variants_match_the_search() {
+        for variant in [Variant::X, Variant::Hyper, Variant::AntiKnight, Variant::AntiKing, Variant::Disjoint] {
+            let shape = Shape::classic().with_variants(&[variant]);
+            check_against_search(&generate(&mut Rng::new(1), &shape, Symmetry::None).unwrap());
+        }
+        check_against_search(&generate(&mut Rng::new(1), &Shape::parse("2x3").unwrap(), Symmetry::None).unwrap());
+        let mut jigsaw = GameBoard::new();
+        jigsaw.init_board_from_string("009000007200009000000006009001035000000000090600000043006000000002400001000600000".to_string());
+        jigsaw.set_rules(Some("111233333111232223411222623471555663475555666477588966447589996447789899477888899".to_string()), &[], None);
+        check_against_search(&jigsaw);
+    }
+
+    #[test]
+    fn uniqueness_check() {
+        assert_eq!(board(PUZZLE, "").run_sat(true).unique, Some(true));
+        assert_eq!(board(PUZZLE, "").run_sat(false).unique, None);
+        // without its first given the puzzle has more than one solution
+        let open = PUZZLE.replacen('2', "0", 1);
+        assert_eq!(board(&open, "").run_sat(true).unique, Some(false));
+        let broken = board(&PUZZLE.replacen('0', "2", 1), "").run_sat(true);
+        assert!(!broken.solved);
+        assert_eq!(broken.unique, None);
+    }
+
+    #[test]
+    fn dimacs_header() {
+        let dimacs = board(PUZZLE, "").to_dimacs();
+        let lines: Vec<&str> = dimacs.lines().collect();
+        assert_eq!(lines[0], format!("c sudoku 3x3 puzzle {}", PUZZLE));
+        assert_eq!(lines[1], "c variable ((row-1)*9 + col-1)*9 + value is true when r<row>c<col> holds value, variables above 729 are running totals");
+        // 81 cells with a value each and no two, 27 units with each value and no repeats, and one clause per given
+        assert_eq!(lines[2], "p cnf 729 12016");
+        assert_eq!(lines.len(), 3 + 12016);
+        assert!(lines[3..].iter().all(|line| line.ends_with(" 0")));
+
+        let killer = board(&"0".repeat(81), include_str!("../examples/killer.txt")).to_dimacs();
+        assert_eq!(killer.lines().nth(2), Some("p cnf 1755 18918"));
+        let mut small = GameBoard::with_shape(Shape::parse("2x3").unwrap());
+        small.init_board_from_string("0".repeat(36));
+        assert_eq!(small.to_dimacs().lines().nth(2), Some("p cnf 216 2304"));
+    }
+}
@@ -0,0 +1,329 @@
+//-----------------------------------------------------------------------------
+// Iterative Search
+//   Depth first search over guesses using an explicit stack instead of
+//   recursion, so large boards can't run out of call stack. Rather than copy
+//   the board for every guess, each change is written to a trail holding the
+//   cell as it was before. Backing up to a guess undoes the trail to where it
+//   stood when the guess was made. Every write to a cell goes through
+//   GameBoard::cell_mut, which saves the cell to the trail the first time it
+//   is written after each guess.
+//   SolveOptions put limits on the search so a pathological puzzle can't
+//   run forever. The limits are checked before each guess
+//-----------------------------------------------------------------------------
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use crate::{GameBoard, GameCell};
+use crate::branching::{Branch, Heuristic};
+use crate::random::Rng;
+
+/// Shared flag that stops a search from another thread
+#[derive(Clone,Debug,Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// asks every search holding a copy of the token to give up
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Limits on the guessing done by a solve, None meaning no limit, and how it picks its guesses
+#[derive(Clone,Debug,Default)]
+pub struct SolveOptions {
+    pub max_nodes: Option<usize>, // the most guesses to try
+    pub max_time: Option<Duration>,
+    pub cancel_token: Option<CancelToken>,
+    pub heuristic: Heuristic,
+    pub seed: u64, // for the random heuristic
+}
+
+/// The limit that made a search give up
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Limit {
+    Nodes,
+    Time,
+    Cancelled,
+}
+impl fmt::Display for Limit {
+    fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Nodes => write!(f, "guess limit reached"),
+            Limit::Time => write!(f, "timed out"),
+            Limit::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// How much guessing a solve did. A solve searches at most once, so these count the nodes of that one search
+#[derive(Clone,Copy,Debug,Default)]
+pub struct SearchStats {
+    pub nodes: usize, // guesses tried
+    pub dead_ends: usize, // guesses that led to a contradiction
+    pub elapsed: Duration,
+}
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} guesses, {} dead ends in {:.2}s", self.nodes, self.dead_ends, self.elapsed.as_secs_f64())
+    }
+}
+
+/// How a solve ended
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Status {
+    Solved,
+    NoSolution,
+    GaveUp(Limit),
+}
+
+/// How a solve ended along with the guessing it did, which is partial when it gave up
+#[derive(Clone,Copy,Debug)]
+pub struct SolveResult {
+    pub status: Status,
+    pub stats: SearchStats,
+}
+
+/// Keeps count of the guesses made by a search and stops it once a limit is passed
+pub struct Budget {
+    options: SolveOptions,
+    start: Instant,
+    pub stats: SearchStats,
+    pub stopped: Option<Limit>, // set when a search gave up
+    pub rng: Rng,
+}
+impl Budget {
+    pub fn new(options:SolveOptions) -> Budget {
+        let rng = Rng::new(options.seed);
+        Budget{options, start:Instant::now(), stats:SearchStats::default(), stopped:None, rng}
+    }
+
+    /// no limits, for searches that always run to the end
+    pub fn unlimited() -> Budget {
+        Budget::new(SolveOptions::default())
+    }
+
+    pub fn heuristic(&self) -> Heuristic {
+        self.options.heuristic
+    }
+
+    /// counts a guess, or returns the limit that rules it out
+    fn spend(&mut self) -> Option<Limit> {
+        if self.options.cancel_token.as_ref().is_some_and(|token| token.is_cancelled()) {
+            self.stopped = Some(Limit::Cancelled);
+        } else if self.options.max_time.is_some_and(|max| self.start.elapsed() >= max) {
+            self.stopped = Some(Limit::Time);
+        } else if self.options.max_nodes.is_some_and(|max| self.stats.nodes >= max) {
+            self.stopped = Some(Limit::Nodes);
+        } else {
+            self.stats.nodes += 1;
+        }
+        self.stopped
+    }
+
+    /// the result of a solve ending with `status`
+    pub fn finish(&mut self, status:Status) -> SolveResult {
+        self.stats.elapsed = self.start.elapsed();
+        SolveResult{status, stats:self.stats}
+    }
+}
+
+/// Every change made to the board since the search started, oldest first
+#[derive(Debug)]
+pub struct Trail {
+    changes: Vec<(usize, GameCell)>, // a cell index and what the cell held before the change
+    saved: Vec<usize>, // the guess each cell was last saved for, one entry per cell in board order
+    guess: usize, // numbers every guess so a cell is saved once per guess
+}
+
+/// the alternatives of a guess along with how far through them the search is
+struct Guess {
+    choices: Branch,
+    next: usize, // index into choices of the next one to try
+    mark: usize, // length of the trail when the guess was made
+}
+
+/// how a round of propagation left the board
+enum Outcome {
+    Solved,
+    Stuck,
+    Unsolvable,
+}
+
+impl Trail {
+    fn new(cells:usize) -> Trail {
+        Trail{changes:Vec::new(), saved:vec![0; cells], guess:1}
+    }
+
+    /// saves cell k before it is written, unless it was already saved since the last guess
+    pub fn save(&mut self, k:usize, cell:&GameCell) {
+        if self.saved[k] != self.guess {
+            self.saved[k] = self.guess;
+            self.changes.push((k, cell.clone()));
+        }
+    }
+
+}
+
+impl GameBoard {
+    /// starts saving the cells written to, so they can be undone
+    pub(crate) fn start_trail(&mut self) {
+        self.trail = Some(Trail::new(self.shape.cell_count()));
+    }
+
+    /// stops saving the cells written to and forgets the saved ones
+    pub(crate) fn end_trail(&mut self) {
+        self.trail = None;
+    }
+
+    /// the length of the trail, to undo back to later
+    pub(crate) fn trail_mark(&self) -> usize {
+        self.trail.as_ref().map_or(0, |trail| trail.changes.len())
+    }
+
+    /// puts the board back to how it was when the trail was `mark` changes long, and starts a new guess
+    pub(crate) fn undo_to(&mut self, mark:usize) {
+        let Some(trail) = &mut self.trail else {return};
+        let size = self.shape.size;
+        while trail.changes.len() > mark {
+            let (k, before) = trail.changes.pop().unwrap();
+            *self.board[k/size][k%size].get_mut() = before;
+        }
+        trail.guess += 1;
+    }
+
+    /// propagates until no more cells get solved, the same way the solve loop does
+    fn settle(&mut self) -> Outcome {
+        loop {
+            let updated = self.propagate();
+            self.set_previously_solved_cells();
+            if self.is_unsolvable() {return Outcome::Unsolvable;}
+            if self.is_solved() {return Outcome::Solved;}
+            if !updated {return Outcome::Stuck;}
+        }
+    }
+
+    /// searches for solutions, guessing as the budget's heuristic picks and then `order` rearranges. Stops once
+    /// `limit` solutions are found and leaves the board on the last one, otherwise the board is put back how it started.
+    /// Returns the number of solutions found, which is only a lower bound if the budget ran out
+    pub fn search(&mut self, limit:usize, order:impl FnMut(&mut Branch), budget:&mut Budget) -> usize {
+        self.explore(limit, order, budget).0
+    }
+
+    /// searches for the first solution and leaves the board on it. Returns the guesses that led there, in the order
+    /// they were made, or None if there is no solution or the budget ran out
+    pub fn search_path(&mut self, order:impl FnMut(&mut Branch), budget:&mut Budget) -> Option<Branch> {
+        let (count, path) = self.explore(1, order, budget);
+        (count > 0).then_some(path)
+    }
+
+    /// the search itself. Also returns the guesses leading to the last solution found
+    fn explore(&mut self, limit:usize, mut order:impl FnMut(&mut Branch), budget:&mut Budget) -> (usize, Branch) {
+        let record_steps = self.record_steps;
+        let steps_start = self.steps.len();
+        self.record_steps = false;
+        self.start_trail();
+        let mut guesses: Vec<Guess> = Vec::new();
+        let mut count = 0;
+        let mut path = Vec::new();
+        loop {
+            match self.settle() {
+                Outcome::Solved => {
+                    count += 1;
+                    path = guesses.iter().map(|guess| guess.choices[guess.next-1]).collect();
+                    if count >= limit {break;}
+                }
+                Outcome::Stuck => {
+                    let mut choices = self.branch(budget.heuristic(), &mut budget.rng);
+                    order(&mut choices);
+                    guesses.push(Guess{choices, next:0, mark:self.trail_mark()});
+                }
+                Outcome::Unsolvable => budget.stats.dead_ends += 1,
+            }
+            // move on to the next untried value of the most recent guess, dropping guesses with none left.
+            // Running out of guesses or out of budget puts the board back how it started
+            while guesses.last().is_some_and(|guess| guess.next == guess.choices.len()) {
+                guesses.pop();
+            }
+            let Some(guess) = guesses.last_mut().filter(|_| budget.spend().is_none()) else {
+                self.undo_to(0);
+                break;
+            };
+            self.undo_to(guess.mark);
+            let (cell,value) = guess.choices[guess.next];
+            self.cell_mut(cell).set_value(value);
+            self.cell_mut(cell).set_guessed();
+            guess.next += 1;
+        }
+        self.end_trail();
+        self.steps.truncate(steps_start);
+        self.record_steps = record_steps;
+        (count, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Shape;
+
+    const PUZZLE: &str = "002000063009000001006000400020180070900760000070490816000800007300040008008000940";
+
+    /// everything undo has to put back, for each cell in board order
+    fn snapshot(board:&GameBoard) -> Vec<(usize, Vec<usize>, bool, bool)> {
+        board.shape.cells().map(|(i,j)| {
+            let cell = board.board[i][j].borrow();
+            (cell.value, cell.possible_values.clone(), cell.hidden_single, cell.state == crate::CellState::Guess)
+        }).collect()
+    }
+
+    #[test]
+    fn undo_restores_the_board_from_before_a_guess() {
+        let mut board = GameBoard::new();
+        board.init_board_from_string(PUZZLE.to_string());
+        board.start_trail();
+        assert!(matches!(board.settle(), Outcome::Stuck));
+        let before = snapshot(&board);
+        let mark = board.trail_mark();
+        let cell = board.shape.cells().find(|&(i,j)| board.board[i][j].borrow().value == 0).unwrap();
+        let candidates = board.board[cell.0][cell.1].borrow().possible_values.clone();
+        let mut contradictions = 0;
+        for value in candidates {
+            // the same as explore, which undoes to a guess's mark before each value so the cells get saved again
+            board.undo_to(mark);
+            board.cell_mut(cell).set_value(value);
+            board.cell_mut(cell).set_guessed();
+            if matches!(board.settle(), Outcome::Unsolvable) {
+                contradictions += 1;
+            }
+            assert_ne!(snapshot(&board), before);
+            board.undo_to(mark);
+            assert_eq!(snapshot(&board), before);
+        }
+        assert!(contradictions > 0);
+        board.end_trail();
+    }
+
+    /// searches an empty board on a thread with a small stack, which a search recursing once per guess would overflow
+    fn fill_on_small_stack(box_size:usize) {
+        std::thread::Builder::new().stack_size(256 * 1024).spawn(move || {
+            let mut board = GameBoard::with_shape(Shape::new(box_size, box_size));
+            assert_eq!(board.search(1, |_| {}, &mut Budget::unlimited()), 1);
+            assert!(board.is_solved());
+        }).unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn deep_searches_run_without_recursion() {
+        fill_on_small_stack(4);
+        fill_on_small_stack(5);
+    }
+}
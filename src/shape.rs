@@ -0,0 +1,331 @@
+//-----------------------------------------------------------------------------
+// Board Shape
+//   The size of the board and of its boxes. A board is box_height*box_width
+//   cells on a side and holds the values 1..=size, so a classic puzzle is 3x3
+//   boxes on a 9x9 board, a kids' puzzle 2x3 boxes on a 6x6 board, etc.
+//   Jigsaw puzzles replace the boxes with irregular regions of the same size,
+//   and variants add extra units that must also hold every value once
+//-----------------------------------------------------------------------------
+
+use std::fmt;
+use std::sync::Arc;
+
+/// The value alphabets, sliced to fit the board size by `Shape::symbols`
+const ALPHANUMERIC: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// A row, column, or square (box) of the board. Indexes are zero based like the board itself
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Unit {
+    Row(usize),
+    Col(usize),
+    Square(usize),
+    Diagonal(usize), // 0 is the main diagonal from the top left, 1 the one from the top right
+    Window(usize), // the extra boxes of a hyper puzzle, counting left to right then top to bottom
+    Group(usize), // the cells at the same spot in every box, counting the spots left to right then top to bottom
+}
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unit::Row(i) => write!(f,"row {}",i+1),
+            Unit::Col(i) => write!(f,"column {}",i+1),
+            Unit::Square(i) => write!(f,"square {}",i+1),
+            Unit::Diagonal(0) => write!(f,"the main diagonal"),
+            Unit::Diagonal(_) => write!(f,"the anti diagonal"),
+            Unit::Window(i) => write!(f,"window {}",i+1),
+            Unit::Group(i) => write!(f,"disjoint group {}",i+1),
+        }
+    }
+}
+
+/// Extra rules on top of the usual rows, columns, and squares
+#[derive(Clone,Copy,Debug,PartialEq,Eq,clap::ValueEnum)]
+pub enum Variant {
+    X, // both main diagonals hold every value once
+    Hyper, // the windows between the boxes hold every value once, also called windoku
+    AntiKnight, // cells a knight's move apart can't hold the same value
+    AntiKing, // cells a king's move apart, including diagonally, can't hold the same value
+    Disjoint, // the cells at the same spot in every box hold every value once
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Shape {
+    pub box_height: usize,
+    pub box_width: usize,
+    pub size: usize, // cells on a side, and the largest value
+    regions: Vec<usize>, // the square each cell belongs to, in row order
+    pub variants: Vec<Variant>,
+    peers: Arc<Vec<Vec<(usize,usize)>>>, // the cells each cell sees, in row order. Shared by every copy of the shape
+}
+impl Shape {
+    pub fn new(box_height:usize, box_width:usize) -> Shape {
+        let size = box_height*box_width;
+        if !(2..=36).contains(&size) {
+            panic!("Boards must be between 2 and 36 cells on a side");
+        }
+        let regions = Shape::box_regions(box_height, box_width);
+        Shape{box_height,box_width,size,regions,variants:Vec::new(),peers:Arc::default()}.with_peers()
+    }
+
+    /// The square each cell belongs to when the squares are plain boxes
+    fn box_regions(box_height:usize, box_width:usize) -> Vec<usize> {
+        let size = box_height*box_width;
+        let boxes_across = size/box_width;
+        (0..size*size).map(|k| (k/size/box_height)*boxes_across + (k%size)/box_width).collect()
+    }
+
+    /// Works out the peers of every cell again after the units or variants change
+    fn with_peers(mut self) -> Shape {
+        let mut peers = vec![Vec::new(); self.cell_count()];
+        for unit in self.units() {
+            let cells = self.unit_cells(unit);
+            for &(i,j) in &cells {
+                peers[i*self.size + j].extend(cells.iter().copied());
+            }
+        }
+        for &variant in &self.variants {
+            for (i,j) in self.cells() {
+                peers[i*self.size + j].extend(self.move_peers((i,j), variant));
+            }
+        }
+        for (k, cell_peers) in peers.iter_mut().enumerate() {
+            cell_peers.sort_unstable();
+            cell_peers.dedup();
+            cell_peers.retain(|&(i,j)| i*self.size + j != k);
+        }
+        self.peers = Arc::new(peers);
+        self
+    }
+
+    /// Replaces the boxes with the regions of a jigsaw puzzle. The map has one character per cell and cells with
+    /// the same character are in the same region, e.g. 111222333... Regions are numbered in the order they first appear
+    pub fn with_regions(mut self, map:&str) -> Result<Shape,String> {
+        let map: Vec<char> = map.chars().filter(|c| !c.is_whitespace()).collect();
+        if map.len() != self.cell_count() {
+            return Err(format!("the region map must be exactly {} characters long", self.cell_count()));
+        }
+        let mut names: Vec<char> = Vec::new();
+        for (k,c) in map.iter().enumerate() {
+            if !names.contains(c) {
+                names.push(*c);
+            }
+            self.regions[k] = names.iter().position(|n| n == c).unwrap();
+        }
+        if names.len() != self.size {
+            return Err(format!("the region map must have exactly {} regions, not {}", self.size, names.len()));
+        }
+        for (region,name) in names.iter().enumerate() {
+            let cells = self.unit_cells(Unit::Square(region));
+            if cells.len() != self.size {
+                return Err(format!("region '{}' has {} cells instead of {}", name, cells.len(), self.size));
+            }
+            if !self.is_connected(&cells) {
+                return Err(format!("region '{}' is not connected", name));
+            }
+        }
+        Ok(self.with_peers())
+    }
+
+    /// True if the cells can all be reached from each other through neighbours above, below, left, or right
+    fn is_connected(&self, cells:&[(usize,usize)]) -> bool {
+        let mut reached = vec![cells[0]];
+        let mut k = 0;
+        while k < reached.len() {
+            let (i,j) = reached[k];
+            for &next in cells {
+                if i.abs_diff(next.0) + j.abs_diff(next.1) == 1 && !reached.contains(&next) {
+                    reached.push(next);
+                }
+            }
+            k += 1;
+        }
+        reached.len() == cells.len()
+    }
+
+    /// Adds the rules of the variants to the board
+    pub fn with_variants(mut self, variants:&[Variant]) -> Shape {
+        for &variant in variants {
+            if !self.variants.contains(&variant) {
+                self.variants.push(variant);
+            }
+        }
+        self.with_peers()
+    }
+
+    /// The units added by the variants, which must hold every value once like rows, columns, and squares
+    pub fn extra_units(&self) -> Vec<Unit> {
+        let mut returned = Vec::new();
+        for variant in &self.variants {
+            match variant {
+                Variant::X => returned.extend([Unit::Diagonal(0), Unit::Diagonal(1)]),
+                Variant::Hyper => returned.extend((0..self.window_starts(self.box_height).len()*self.window_starts(self.box_width).len()).map(Unit::Window)),
+                Variant::Disjoint => returned.extend((0..self.size).map(Unit::Group)),
+                Variant::AntiKnight | Variant::AntiKing => {}
+            }
+        }
+        returned
+    }
+
+    /// The cells a chess move away from a cell under the anti-knight or anti-king variant, which can't share its value.
+    /// Other variants don't add any peers this way
+    pub fn move_peers(&self, cell:(usize,usize), variant:Variant) -> Vec<(usize,usize)> {
+        let moves: &[(isize,isize)] = match variant {
+            Variant::AntiKnight => &[(-2,-1),(-2,1),(-1,-2),(-1,2),(1,-2),(1,2),(2,-1),(2,1)],
+            Variant::AntiKing => &[(-1,-1),(-1,0),(-1,1),(0,-1),(0,1),(1,-1),(1,0),(1,1)],
+            Variant::X | Variant::Hyper | Variant::Disjoint => &[],
+        };
+        moves.iter().filter_map(|&(di,dj)| {
+            let (i,j) = (cell.0.checked_add_signed(di)?, cell.1.checked_add_signed(dj)?);
+            (i < self.size && j < self.size).then_some((i,j))
+        }).collect()
+    }
+
+    /// The top rows of the hyper windows when `span` is the box height, or their left columns when it is the box width.
+    /// Windows sit one cell in from the boxes with a one cell gap between them, so a 9x9 board has windows starting at 1 and 5
+    fn window_starts(&self, span:usize) -> Vec<usize> {
+        (0..).map(|k| 1 + k*(span+1)).take_while(|&start| start + span < self.size).collect()
+    }
+
+    /// True if the squares are jigsaw regions rather than boxes
+    pub fn is_jigsaw(&self) -> bool {
+        self.regions != Shape::box_regions(self.box_height,self.box_width)
+    }
+
+    /// The classic 9x9 board
+    pub fn classic() -> Shape {
+        Shape::new(3,3)
+    }
+
+    /// Picks the usual shape for a puzzle with this many cells. Rectangular boxes are wider than they are tall
+    pub fn from_cell_count(cells:usize) -> Option<Shape> {
+        match cells {
+            16 => Some(Shape::new(2,2)),
+            36 => Some(Shape::new(2,3)),
+            64 => Some(Shape::new(2,4)),
+            81 => Some(Shape::new(3,3)),
+            144 => Some(Shape::new(3,4)),
+            256 => Some(Shape::new(4,4)),
+            625 => Some(Shape::new(5,5)),
+            _ => None,
+        }
+    }
+
+    /// Reads a box size written as HEIGHTxWIDTH, e.g. 2x3
+    pub fn parse(text:&str) -> Result<Shape,String> {
+        let (h,w) = text.split_once('x').ok_or_else(|| format!("box size must look like 2x3, not '{}'",text))?;
+        match (h.parse::<usize>(), w.parse::<usize>()) {
+            (Ok(h),Ok(w)) if (2..=36).contains(&(h*w)) => Ok(Shape::new(h,w)),
+            _ => Err(format!("invalid box size '{}'",text)),
+        }
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.size*self.size
+    }
+
+    /// The characters used for the values 1..=size. Classic boards use digits, 16x16 uses the hex digits 0-F,
+    /// 25x25 uses the letters A-Y, 36x36 uses 0-9 then A-Z, and other sizes count on from 9 with letters
+    pub fn symbols(&self) -> &'static str {
+        match self.size {
+            16 => &ALPHANUMERIC[..16],
+            25 => &LETTERS[..25],
+            36 => ALPHANUMERIC,
+            _ => &ALPHANUMERIC[1..=self.size.min(35)],
+        }
+    }
+
+    /// The character for an unsolved cell. '0' unless 0 is a value, when '.' is used instead
+    pub fn empty_symbol(&self) -> char {
+        if self.symbols().starts_with('0') {'.'} else {'0'}
+    }
+
+    /// The character for a value, or the empty symbol for 0
+    pub fn symbol(&self, value:usize) -> char {
+        if value == 0 {self.empty_symbol()} else {self.symbols().as_bytes()[value-1] as char}
+    }
+
+    /// Reads the value of a character. '.' is always unsolved, and so is '0' when it isn't a value
+    pub fn value_of(&self, c:char) -> Option<usize> {
+        if c == '.' || c == self.empty_symbol() {
+            return Some(0);
+        }
+        self.symbols().chars().position(|s| s == c.to_ascii_uppercase()).map(|k| k+1)
+    }
+
+    /// Number of boxes across the board
+    fn boxes_across(&self) -> usize {
+        self.size/self.box_width
+    }
+
+    /// The cells that make up the unit in board order
+    pub fn unit_cells(&self, unit:Unit) -> Vec<(usize,usize)> {
+        match unit {
+            Unit::Row(i) => (0..self.size).map(|j| (i,j)).collect(),
+            Unit::Col(i) => (0..self.size).map(|j| (j,i)).collect(),
+            Unit::Square(i) => self.cells().filter(|&c| self.square_of(c) == i).collect(),
+            Unit::Diagonal(0) => (0..self.size).map(|k| (k,k)).collect(),
+            Unit::Diagonal(_) => (0..self.size).map(|k| (k,self.size-1-k)).collect(),
+            Unit::Window(i) => {
+                let (tops, lefts) = (self.window_starts(self.box_height), self.window_starts(self.box_width));
+                let (top, left) = (tops[i/lefts.len()], lefts[i%lefts.len()]);
+                (0..self.size).map(|k| (top + k/self.box_width, left + k%self.box_width)).collect()
+            }
+            Unit::Group(k) => self.cells().filter(|&(i,j)| (i%self.box_height)*self.box_width + j%self.box_width == k).collect(),
+        }
+    }
+
+    /// Every unit on the board. Squares come first since people usually scan them first, and the variants' units last
+    pub fn units(&self) -> Vec<Unit> {
+        let mut returned: Vec<Unit> = (0..self.size).map(Unit::Square).collect();
+        returned.extend((0..self.size).map(Unit::Row));
+        returned.extend((0..self.size).map(Unit::Col));
+        returned.extend(self.extra_units());
+        returned
+    }
+
+    /// The index of the square a cell is in, counting left to right then top to bottom
+    pub fn square_of(&self, cell:(usize,usize)) -> usize {
+        self.regions[cell.0*self.size + cell.1]
+    }
+
+    /// True if two different cells share a row, column, square, or one of the variants' units, or are a chess move apart
+    pub fn sees(&self, a:(usize,usize), b:(usize,usize)) -> bool {
+        self.peers(a).binary_search(&b).is_ok()
+    }
+
+    /// Every cell seen by a cell, in row order
+    pub fn peers(&self, cell:(usize,usize)) -> &[(usize,usize)] {
+        &self.peers[cell.0*self.size + cell.1]
+    }
+
+    /// Every cell of the board in row order
+    pub fn cells(&self) -> impl Iterator<Item = (usize,usize)> {
+        let size = self.size;
+        (0..size*size).map(move |k| (k/size, k%size))
+    }
+
+    /// True if column j is the last column of a box that isn't on the right edge
+    pub fn is_box_right_edge(&self, j:usize) -> bool {
+        (j+1).is_multiple_of(self.box_width) && j+1 != self.size
+    }
+
+    /// True if row i is the last row of a box that isn't on the bottom edge
+    pub fn is_box_bottom_edge(&self, i:usize) -> bool {
+        (i+1).is_multiple_of(self.box_height) && i+1 != self.size
+    }
+
+    /// A horizontal line between bands of boxes with each box `width` characters wide, e.g. ---+---+---
+    pub fn separator_line(&self, width:usize) -> String {
+        vec!["-".repeat(width*self.box_width); self.boxes_across()].join("+")
+    }
+}
+impl Default for Shape {
+    fn default() -> Shape {
+        Shape::classic()
+    }
+}
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,"{}x{}",self.box_height,self.box_width)
+    }
+}
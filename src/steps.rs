@@ -0,0 +1,276 @@
+//-----------------------------------------------------------------------------
+// Step Log
+//   Every placement and elimination made by the solver is recorded as a Step
+//   so the solve can be explained to a person or exported to another program
+//-----------------------------------------------------------------------------
+
+use std::fmt;
+pub use crate::shape::Unit;
+
+/// The rule that caused a step to happen
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub enum Technique {
+    Elimination, // a solved cell removes its value from the rest of a row, column, or square
+    NakedSingle, // a cell has only one possible value left
+    HiddenSingle, // a value has only one possible cell left in a row, column, or square
+    ShortSegment, // the cells where a row/col crosses a square hold only as many values as there are cells
+    Pointing, // a value in a square is confined to one row/col so it is removed from the rest of that row/col
+    Claiming, // a value in a row/col is confined to one square so it is removed from the rest of that square
+    CageSum, // a killer cage can only hold the values of the sets that make its sum
+    InnieOutie, // the cells poking in or out of a group of rows, columns, or a square make up the rest of its total
+    Kropki, // a dot between neighbouring cells, or the lack of one, rules out values that don't fit the other cell
+    Thermo, // values rise along a thermometer so each cell is bounded by its neighbours on it
+    Arrow, // the values along an arrow add up to its circle so each is bounded by the others
+    XV, // neighbours with an X or V between them add up to 10 or 5
+    Sandwich, // the values between the smallest and largest in a row or column make up the sandwich sum
+    LittleKiller, // the values along a diagonal add up to the little killer clue
+    Whisper, // neighbours on a German whispers line are at least 5 apart
+    Renban, // a renban line holds a run of consecutive values
+    Palindrome, // a palindrome line reads the same from either end
+    Parity, // a cell shaded even or odd can't hold values of the other parity
+    GreaterThan, // the values on either side of a greater-than sign are bounded by each other
+    Custom, // a rule added with GameBoard::add_constraint
+    NakedPair, // two cells in a unit hold the same two values
+    XWing, // a value confined to the same two columns in two rows, or the same two rows in two columns
+    HiddenPair, // two values in a unit only fit in the same two cells
+    NakedTriple, // three cells in a unit hold three values between them
+    Swordfish, // the three row/col version of an x-wing
+    HiddenTriple, // three values in a unit only fit in the same three cells
+    XYWing, // a two value cell sees two cells that share its values and a third value which is removed from cells seeing both
+    XYZWing, // an xy-wing whose pivot also holds the value being removed
+    NakedQuad, // four cells in a unit hold four values between them
+    Jellyfish, // the four row/col version of an x-wing
+    HiddenQuad, // four values in a unit only fit in the same four cells
+    Guess, // a value picked by the search
+}
+impl Technique {
+    /// Human readable name used when printing steps
+    pub fn name(&self) -> &'static str {
+        match self {
+            Technique::Elimination => "Elimination",
+            Technique::NakedSingle => "Naked single",
+            Technique::HiddenSingle => "Hidden single",
+            Technique::ShortSegment => "Short segment",
+            Technique::Pointing => "Pointing",
+            Technique::Claiming => "Claiming",
+            Technique::CageSum => "Cage sum",
+            Technique::InnieOutie => "Innie/outie",
+            Technique::Kropki => "Kropki dot",
+            Technique::Thermo => "Thermometer",
+            Technique::Arrow => "Arrow",
+            Technique::XV => "XV",
+            Technique::Sandwich => "Sandwich",
+            Technique::LittleKiller => "Little killer",
+            Technique::Whisper => "German whispers",
+            Technique::Renban => "Renban",
+            Technique::Palindrome => "Palindrome",
+            Technique::Parity => "Even/odd",
+            Technique::GreaterThan => "Greater than",
+            Technique::Custom => "Custom rule",
+            Technique::NakedPair => "Naked pair",
+            Technique::XWing => "X-Wing",
+            Technique::HiddenPair => "Hidden pair",
+            Technique::NakedTriple => "Naked triple",
+            Technique::Swordfish => "Swordfish",
+            Technique::HiddenTriple => "Hidden triple",
+            Technique::XYWing => "XY-Wing",
+            Technique::XYZWing => "XYZ-Wing",
+            Technique::NakedQuad => "Naked quad",
+            Technique::Jellyfish => "Jellyfish",
+            Technique::HiddenQuad => "Hidden quad",
+            Technique::Guess => "Guess",
+        }
+    }
+
+    /// Machine readable name used in the JSON export
+    pub fn id(&self) -> &'static str {
+        match self {
+            Technique::Elimination => "elimination",
+            Technique::NakedSingle => "naked_single",
+            Technique::HiddenSingle => "hidden_single",
+            Technique::ShortSegment => "short_segment",
+            Technique::Pointing => "pointing",
+            Technique::Claiming => "claiming",
+            Technique::CageSum => "cage_sum",
+            Technique::InnieOutie => "innie_outie",
+            Technique::Kropki => "kropki",
+            Technique::Thermo => "thermo",
+            Technique::Arrow => "arrow",
+            Technique::XV => "xv",
+            Technique::Sandwich => "sandwich",
+            Technique::LittleKiller => "little_killer",
+            Technique::Whisper => "whisper",
+            Technique::Renban => "renban",
+            Technique::Palindrome => "palindrome",
+            Technique::Parity => "parity",
+            Technique::GreaterThan => "greater_than",
+            Technique::Custom => "custom",
+            Technique::NakedPair => "naked_pair",
+            Technique::XWing => "xwing",
+            Technique::HiddenPair => "hidden_pair",
+            Technique::NakedTriple => "naked_triple",
+            Technique::Swordfish => "swordfish",
+            Technique::HiddenTriple => "hidden_triple",
+            Technique::XYWing => "xywing",
+            Technique::XYZWing => "xyzwing",
+            Technique::NakedQuad => "naked_quad",
+            Technique::Jellyfish => "jellyfish",
+            Technique::HiddenQuad => "hidden_quad",
+            Technique::Guess => "guess",
+        }
+    }
+
+    /// Looks up a technique by the name used in the JSON export
+    pub fn from_id(id:&str) -> Option<Technique> {
+        ALL_TECHNIQUES.iter().copied().find(|t| t.id() == id)
+    }
+
+    /// True if the technique places a value rather than only removing possible values
+    pub fn is_placement(&self) -> bool {
+        matches!(self, Technique::NakedSingle | Technique::HiddenSingle | Technique::Guess)
+    }
+}
+/// Every technique, in the order they are declared
+pub const ALL_TECHNIQUES: [Technique; 32] = [
+    Technique::Elimination,
+    Technique::NakedSingle,
+    Technique::HiddenSingle,
+    Technique::ShortSegment,
+    Technique::Pointing,
+    Technique::Claiming,
+    Technique::CageSum,
+    Technique::InnieOutie,
+    Technique::Kropki,
+    Technique::Thermo,
+    Technique::Arrow,
+    Technique::XV,
+    Technique::Sandwich,
+    Technique::LittleKiller,
+    Technique::Whisper,
+    Technique::Renban,
+    Technique::Palindrome,
+    Technique::Parity,
+    Technique::GreaterThan,
+    Technique::Custom,
+    Technique::NakedPair,
+    Technique::XWing,
+    Technique::HiddenPair,
+    Technique::NakedTriple,
+    Technique::Swordfish,
+    Technique::HiddenTriple,
+    Technique::XYWing,
+    Technique::XYZWing,
+    Technique::NakedQuad,
+    Technique::Jellyfish,
+    Technique::HiddenQuad,
+    Technique::Guess,
+];
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,"{}",self.name())
+    }
+}
+
+/// A single deduction. For placements `cells` and `digits` hold the placed cell and value,
+/// for eliminations they hold every cell that lost a possible value and the values removed
+#[derive(Clone,Debug)]
+pub struct Step {
+    pub technique: Technique,
+    pub cells: Vec<(usize,usize)>,
+    pub digits: Vec<usize>,
+    pub reason: String,
+    pub unit: Option<Unit>, // the unit the deduction was made in, when it was made in a single unit
+}
+impl Step {
+    pub fn new(technique:Technique, cells:Vec<(usize,usize)>, digits:Vec<usize>, reason:String) -> Step {
+        Step{technique,cells,digits,reason,unit:None}
+    }
+
+    /// Records the unit the deduction was made in
+    pub fn in_unit(mut self, unit:Unit) -> Step {
+        self.unit = Some(unit);
+        self
+    }
+
+    /// Writes the step as a JSON object
+    pub fn to_json(&self) -> String {
+        let cells: Vec<String> = self.cells.iter().map(|c| format!("[{},{}]",c.0+1,c.1+1)).collect();
+        let digits: Vec<String> = self.digits.iter().map(|d| d.to_string()).collect();
+        let unit = match self.unit {
+            Some(unit) => format!("\"{}\"",unit),
+            None => "null".to_string(),
+        };
+        format!("{{\"technique\":\"{}\",\"placement\":{},\"cells\":[{}],\"digits\":[{}],\"unit\":{},\"reason\":\"{}\"}}",
+            self.technique.id(),
+            self.technique.is_placement(),
+            cells.join(","),
+            digits.join(","),
+            unit,
+            escape_json(&self.reason))
+    }
+}
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,"{}: {}",self.technique,self.reason)
+    }
+}
+
+/// Output format for the step log
+#[derive(Clone,Copy,Debug,PartialEq,Eq,clap::ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Prints every step in the log, one per line
+pub fn print_steps(steps:&[Step]) {
+    for step in steps {
+        println!("{}",step);
+    }
+}
+
+/// Writes the whole log as a JSON array
+pub fn steps_to_json(steps:&[Step]) -> String {
+    let items: Vec<String> = steps.iter().map(|s| format!("  {}",s.to_json())).collect();
+    if items.is_empty() {
+        return "[]".to_string();
+    }
+    format!("[\n{}\n]",items.join(",\n"))
+}
+
+/// Formats a cell as r<row>c<col> using one based indexes
+pub fn cell_name(cell:(usize,usize)) -> String {
+    format!("r{}c{}",cell.0+1,cell.1+1)
+}
+
+/// Formats a list of cells as a comma separated list of cell names
+pub fn cell_list(cells:&[(usize,usize)]) -> String {
+    cells.iter().map(|&c| cell_name(c)).collect::<Vec<String>>().join(", ")
+}
+
+/// Formats a list of values as a comma separated list
+pub fn digit_list(digits:&[usize]) -> String {
+    digits.iter().map(|d| d.to_string()).collect::<Vec<String>>().join(",")
+}
+
+fn escape_json(s:&str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}",c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Writes the puzzle, the resulting board, and the step log as a single JSON document
+pub fn export_json(puzzle:&str, result:&str, solved:bool, steps:&[Step]) -> String {
+    format!("{{\n\"puzzle\":\"{}\",\n\"result\":\"{}\",\n\"solved\":{},\n\"steps\":{}\n}}",
+        escape_json(puzzle), escape_json(result), solved, steps_to_json(steps))
+}
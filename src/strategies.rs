@@ -0,0 +1,399 @@
+//-----------------------------------------------------------------------------
+// Human Style Strategies
+//   Solves the board one deduction at a time, always using the easiest
+//   technique that makes progress. Slower than solve_loop but the resulting
+//   step log reads like the way a person would solve the puzzle
+//-----------------------------------------------------------------------------
+
+use std::ops::Deref;
+use crate::GameBoard;
+use crate::compare::Parity;
+use crate::search::Budget;
+use crate::steps::{cell_list, cell_name, digit_list, Step, Technique, Unit};
+
+/// The techniques tried by the human style solver, easiest first
+pub const STRATEGY_ORDER: [Technique; 29] = [
+    Technique::Parity,
+    Technique::HiddenSingle,
+    Technique::CageSum,
+    Technique::Palindrome,
+    Technique::Thermo,
+    Technique::GreaterThan,
+    Technique::Whisper,
+    Technique::XV,
+    Technique::Kropki,
+    Technique::Arrow,
+    Technique::LittleKiller,
+    Technique::Renban,
+    Technique::NakedSingle,
+    Technique::Pointing,
+    Technique::Claiming,
+    Technique::InnieOutie,
+    Technique::Sandwich,
+    Technique::Custom,
+    Technique::NakedPair,
+    Technique::XWing,
+    Technique::HiddenPair,
+    Technique::NakedTriple,
+    Technique::Swordfish,
+    Technique::HiddenTriple,
+    Technique::XYWing,
+    Technique::XYZWing,
+    Technique::NakedQuad,
+    Technique::Jellyfish,
+    Technique::HiddenQuad,
+];
+
+impl GameBoard {
+    /// solves the board using the easiest technique that works at each step, falling back to a guess
+    /// checked against solve_loop when no technique applies. Returns false if the puzzle has no solution
+    pub fn solve_human(&mut self) -> bool {
+        self.set_possible_values_by_row();
+        self.set_possible_values_by_col();
+        self.set_possible_values_by_square();
+        self.set_possible_values_by_extra_units();
+        self.set_possible_values_by_chess_moves();
+        self.set_possible_values_by_cages();
+        loop {
+            if self.is_unsolvable() {return false;}
+            if self.is_solved() {return true;}
+            if !self.apply_easiest_technique() && !self.apply_guess() {
+                return false;
+            }
+        }
+    }
+
+    /// tries each technique in strategy order and applies the first one that makes progress
+    pub fn apply_easiest_technique(&mut self) -> bool {
+        STRATEGY_ORDER.iter().any(|&t| self.apply_technique(t))
+    }
+
+    /// finds and applies the first instance of a technique. Boolean return value indicates changes were made
+    pub fn apply_technique(&mut self, technique:Technique) -> bool {
+        match technique {
+            Technique::NakedSingle => self.apply_naked_single(),
+            Technique::HiddenSingle => self.apply_hidden_single(),
+            Technique::Pointing => self.apply_pointing(),
+            Technique::Claiming => self.apply_claiming(),
+            Technique::CageSum => (0..self.constraints.cages.len()).any(|k| self.filter_cage_sum(k)),
+            Technique::InnieOutie => self.apply_first_45_rule(),
+            Technique::Kropki | Technique::XV => self.apply_first_dot(technique),
+            Technique::Whisper | Technique::Renban | Technique::Palindrome => self.apply_first_line(technique),
+            Technique::Thermo => (0..self.constraints.thermos.len()).any(|k| self.filter_thermo(k)),
+            Technique::GreaterThan => (0..self.constraints.signs.len()).any(|k| self.filter_sign(k)),
+            Technique::Parity => self.filter_parity(Parity::Even) || self.filter_parity(Parity::Odd),
+            Technique::Custom => (0..self.constraints.custom.len()).any(|k| self.apply_custom_constraint(k)),
+            Technique::Arrow => (0..self.constraints.arrows.len()).any(|k| self.filter_arrow(k)),
+            Technique::Sandwich => (0..self.constraints.sandwiches.len()).any(|k| self.filter_sandwich(k)),
+            Technique::LittleKiller => (0..self.constraints.little_killers.len()).any(|k| self.filter_little_killer(k)),
+            Technique::NakedPair => self.apply_naked_subset(2),
+            Technique::NakedTriple => self.apply_naked_subset(3),
+            Technique::NakedQuad => self.apply_naked_subset(4),
+            Technique::HiddenPair => self.apply_hidden_subset(2),
+            Technique::HiddenTriple => self.apply_hidden_subset(3),
+            Technique::HiddenQuad => self.apply_hidden_subset(4),
+            Technique::XWing => self.apply_fish(2),
+            Technique::Swordfish => self.apply_fish(3),
+            Technique::Jellyfish => self.apply_fish(4),
+            Technique::XYWing => self.apply_xy_wing(),
+            Technique::XYZWing => self.apply_xyz_wing(),
+            Technique::Guess => self.apply_guess(),
+            Technique::Elimination | Technique::ShortSegment => false,
+        }
+    }
+
+    /// the possible values of a cell, empty if the cell is solved
+    fn candidates(&self, cell:(usize,usize)) -> Vec<usize> {
+        self.board[cell.0][cell.1].borrow().deref().possible_values.clone()
+    }
+
+    /// the unsolved cells of a unit that can still hold a value
+    fn positions(&self, unit:Unit, value:usize) -> Vec<(usize,usize)> {
+        self.shape.unit_cells(unit).into_iter().filter(|&c| self.candidates(c).contains(&value)).collect()
+    }
+
+    /// sets the cell of a placement step, logs the step, and removes the value from the cell's row, column, and square
+    fn place_value(&mut self, step:Step) {
+        let cell = step.cells[0];
+        let game_cell = self.cell_mut(cell);
+        game_cell.set_value(step.digits[0]);
+        game_cell.set_newly_solved();
+        self.steps.push(step);
+        self.set_possible_values_by_row();
+        self.set_possible_values_by_col();
+        self.set_possible_values_by_square();
+        self.set_possible_values_by_extra_units();
+        self.set_possible_values_by_chess_moves();
+        self.set_possible_values_by_cages();
+    }
+
+    /// removes each value from each cell and returns the cells that changed
+    fn eliminate(&mut self, cells:&[(usize,usize)], values:&[usize]) -> Vec<(usize,usize)> {
+        let mut changed = Vec::new();
+        for &cell in cells {
+            for &value in values {
+                if self.remove_candidate(cell, value) && !changed.contains(&cell) {
+                    changed.push(cell);
+                }
+            }
+        }
+        changed
+    }
+
+    fn apply_naked_single(&mut self) -> bool {
+        for (i,j) in self.shape.cells() {
+            let candidates = self.candidates((i,j));
+            if candidates.len() == 1 {
+                let reason = format!("{} can only be {}", cell_name((i,j)), candidates[0]);
+                self.place_value(Step::new(Technique::NakedSingle, vec![(i,j)], vec![candidates[0]], reason));
+                return true;
+            }
+        }
+        false
+    }
+
+    fn apply_hidden_single(&mut self) -> bool {
+        for unit in self.shape.units() {
+            for value in 1..=self.shape.size {
+                let positions = self.positions(unit, value);
+                if positions.len() == 1 {
+                    let reason = format!("{} in {} at {}", value, unit, cell_name(positions[0]));
+                    self.place_value(Step::new(Technique::HiddenSingle, vec![positions[0]], vec![value], reason).in_unit(unit));
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// a value confined to one row or column of a square can be removed from the rest of that row or column.
+    /// The same goes for any of the variants' units the value is confined to
+    fn apply_pointing(&mut self) -> bool {
+        for sq in 0..self.shape.size {
+            for value in 1..=self.shape.size {
+                let positions = self.positions(Unit::Square(sq), value);
+                if positions.len() < 2 {continue;}
+                for line in self.units_holding(&positions, Unit::Square(sq)).into_iter().filter(|u| !matches!(u, Unit::Square(_))) {
+                    let targets: Vec<(usize,usize)> = self.shape.unit_cells(line).into_iter().filter(|c| !positions.contains(c)).collect();
+                    let changed = self.eliminate(&targets, &[value]);
+                    if !changed.is_empty() {
+                        let reason = format!("{} in {} must be in {} ({}) so it is removed from {}",
+                            value, Unit::Square(sq), line, cell_list(&positions), cell_list(&changed));
+                        self.steps.push(Step::new(Technique::Pointing, changed, vec![value], reason).in_unit(Unit::Square(sq)));
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// a value confined to one square within a row or column can be removed from the rest of that square.
+    /// The variants' units work like rows and columns here, and like squares when a value is confined to one
+    fn apply_claiming(&mut self) -> bool {
+        for line in self.shape.units().into_iter().filter(|u| !matches!(u, Unit::Square(_))) {
+            for value in 1..=self.shape.size {
+                let positions = self.positions(line, value);
+                if positions.len() < 2 {continue;}
+                for target in self.units_holding(&positions, line).into_iter().filter(|u| !matches!(u, Unit::Row(_) | Unit::Col(_))) {
+                    let targets: Vec<(usize,usize)> = self.shape.unit_cells(target).into_iter().filter(|c| !positions.contains(c)).collect();
+                    let changed = self.eliminate(&targets, &[value]);
+                    if !changed.is_empty() {
+                        let reason = format!("{} in {} must be in {} ({}) so it is removed from {}",
+                            value, line, target, cell_list(&positions), cell_list(&changed));
+                        self.steps.push(Step::new(Technique::Claiming, changed, vec![value], reason).in_unit(line));
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// the units other than `unit` that contain every one of the cells
+    fn units_holding(&self, cells:&[(usize,usize)], unit:Unit) -> Vec<Unit> {
+        self.shape.units().into_iter().filter(|&u| {
+            let unit_cells = self.shape.unit_cells(u);
+            u != unit && cells.iter().all(|c| unit_cells.contains(c))
+        }).collect()
+    }
+
+    /// n cells in a unit that hold only n values between them remove those values from the rest of the unit
+    fn apply_naked_subset(&mut self, n:usize) -> bool {
+        let technique = [Technique::NakedPair, Technique::NakedTriple, Technique::NakedQuad][n-2];
+        for unit in self.shape.units() {
+            let open: Vec<(usize,usize)> = self.shape.unit_cells(unit).into_iter().filter(|&c| {
+                let len = self.candidates(c).len();
+                len >= 2 && len <= n
+            }).collect();
+            for combo in combinations(open.len(), n) {
+                let cells: Vec<(usize,usize)> = combo.iter().map(|&k| open[k]).collect();
+                let mut values: Vec<usize> = cells.iter().flat_map(|&c| self.candidates(c)).collect();
+                values.sort();
+                values.dedup();
+                if values.len() != n {continue;}
+                let targets: Vec<(usize,usize)> = self.shape.unit_cells(unit).into_iter().filter(|c| !cells.contains(c)).collect();
+                let changed = self.eliminate(&targets, &values);
+                if !changed.is_empty() {
+                    let reason = format!("{} in {} can only hold {} so they are removed from {}",
+                        cell_list(&cells), unit, digit_list(&values), cell_list(&changed));
+                    self.steps.push(Step::new(technique, changed, values, reason).in_unit(unit));
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// n values in a unit that only fit in n cells remove every other value from those cells
+    fn apply_hidden_subset(&mut self, n:usize) -> bool {
+        let technique = [Technique::HiddenPair, Technique::HiddenTriple, Technique::HiddenQuad][n-2];
+        for unit in self.shape.units() {
+            let open: Vec<usize> = (1..=self.shape.size).filter(|&v| {
+                let len = self.positions(unit, v).len();
+                len >= 2 && len <= n
+            }).collect();
+            for combo in combinations(open.len(), n) {
+                let values: Vec<usize> = combo.iter().map(|&k| open[k]).collect();
+                let mut cells: Vec<(usize,usize)> = values.iter().flat_map(|&v| self.positions(unit, v)).collect();
+                cells.sort();
+                cells.dedup();
+                if cells.len() != n {continue;}
+                let others: Vec<usize> = (1..=self.shape.size).filter(|v| !values.contains(v)).collect();
+                let changed = self.eliminate(&cells, &others);
+                if !changed.is_empty() {
+                    let reason = format!("{} in {} only fit in {} so every other value is removed from them",
+                        digit_list(&values), unit, cell_list(&cells));
+                    self.steps.push(Step::new(technique, changed, values, reason).in_unit(unit));
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// a value confined to the same n columns in n rows is removed from the rest of those columns, and the same with rows and columns swapped
+    fn apply_fish(&mut self, n:usize) -> bool {
+        let technique = [Technique::XWing, Technique::Swordfish, Technique::Jellyfish][n-2];
+        for by_row in [true, false] {
+            for value in 1..=self.shape.size {
+                // lines that hold the value in 2..=n places, with the cross indexes of those places
+                let lines: Vec<(usize,Vec<usize>)> = (0..self.shape.size).filter_map(|i| {
+                    let unit = if by_row {Unit::Row(i)} else {Unit::Col(i)};
+                    let crosses: Vec<usize> = self.positions(unit, value).into_iter()
+                        .map(|c| if by_row {c.1} else {c.0}).collect();
+                    if crosses.len() >= 2 && crosses.len() <= n {Some((i,crosses))} else {None}
+                }).collect();
+                for combo in combinations(lines.len(), n) {
+                    let base: Vec<usize> = combo.iter().map(|&k| lines[k].0).collect();
+                    let mut cover: Vec<usize> = combo.iter().flat_map(|&k| lines[k].1.clone()).collect();
+                    cover.sort();
+                    cover.dedup();
+                    if cover.len() != n {continue;}
+                    let mut targets = Vec::new();
+                    for &x in &cover {
+                        for y in (0..self.shape.size).filter(|y| !base.contains(y)) {
+                            targets.push(if by_row {(y,x)} else {(x,y)});
+                        }
+                    }
+                    let changed = self.eliminate(&targets, &[value]);
+                    if !changed.is_empty() {
+                        let (base_name, cover_name) = if by_row {("rows","columns")} else {("columns","rows")};
+                        let reason = format!("{} in {} {} is confined to {} {} so it is removed from {}",
+                            value, base_name, one_based(&base), cover_name, one_based(&cover), cell_list(&changed));
+                        self.steps.push(Step::new(technique, changed, vec![value], reason));
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// a pivot holding xy sees a pincer holding xz and a pincer holding yz, so z is removed from every cell seeing both pincers
+    fn apply_xy_wing(&mut self) -> bool {
+        self.apply_wing(Technique::XYWing)
+    }
+
+    /// a pivot holding xyz sees a pincer holding xz and a pincer holding yz, so z is removed from every cell seeing all three
+    fn apply_xyz_wing(&mut self) -> bool {
+        self.apply_wing(Technique::XYZWing)
+    }
+
+    fn apply_wing(&mut self, technique:Technique) -> bool {
+        let pivot_size = if technique == Technique::XYWing {2} else {3};
+        let bivalue: Vec<(usize,usize)> = self.shape.cells().filter(|&c| self.candidates(c).len() == 2).collect();
+        for pivot in self.shape.cells() {
+            let pivot_values = self.candidates(pivot);
+            if pivot_values.len() != pivot_size {continue;}
+            let pincers: Vec<(usize,usize)> = bivalue.iter().copied().filter(|&c| c != pivot && self.shape.sees(pivot, c)).collect();
+            for combo in combinations(pincers.len(), 2) {
+                let (a, b) = (pincers[combo[0]], pincers[combo[1]]);
+                let (a_values, b_values) = (self.candidates(a), self.candidates(b));
+                // the pincers share exactly one value and between them hold the pivot's values plus, for an xy-wing, that shared value
+                let shared: Vec<usize> = a_values.iter().copied().filter(|v| b_values.contains(v)).collect();
+                if shared.len() != 1 || a_values == b_values {continue;}
+                let z = shared[0];
+                let mut union: Vec<usize> = a_values.iter().chain(b_values.iter()).copied().collect();
+                union.sort();
+                union.dedup();
+                let fits = if pivot_size == 2 {
+                    !pivot_values.contains(&z) && union.iter().filter(|v| **v != z).all(|v| pivot_values.contains(v))
+                } else {
+                    union == pivot_values
+                };
+                if !fits {continue;}
+                let targets: Vec<(usize,usize)> = self.shape.cells().filter(|&c| {
+                    c != pivot && c != a && c != b && self.shape.sees(c, a) && self.shape.sees(c, b) && (pivot_size == 2 || self.shape.sees(c, pivot))
+                }).collect();
+                let changed = self.eliminate(&targets, &[z]);
+                if !changed.is_empty() {
+                    let reason = format!("pivot {} with pincers {} and {} removes {} from {}",
+                        cell_name(pivot), cell_name(a), cell_name(b), z, cell_list(&changed));
+                    self.steps.push(Step::new(technique, changed, vec![z], reason));
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// places the value of the solution in the unsolved cell with the fewest possible values
+    fn apply_guess(&mut self) -> bool {
+        let target = match self.get_smallest_possible_gamecell_by_idx() {
+            Some(target) => target,
+            None => return false,
+        };
+        let mut solution = self.search_clone();
+        if solution.search(1, |_| {}, &mut Budget::unlimited()) == 0 {
+            return false;
+        }
+        let value = solution.board[target.0][target.1].borrow().value;
+        let reason = format!("{} at {} leads to a solution", value, cell_name(target));
+        self.place_value(Step::new(Technique::Guess, vec![target], vec![value], reason));
+        self.cell_mut(target).set_guessed();
+        true
+    }
+}
+
+/// every way to pick k indexes out of 0..n in ascending order
+pub fn combinations(n:usize, k:usize) -> Vec<Vec<usize>> {
+    fn inner(start:usize, n:usize, k:usize, current:&mut Vec<usize>, out:&mut Vec<Vec<usize>>) {
+        if current.len() == k {
+            out.push(current.clone());
+            return;
+        }
+        for i in start..n {
+            current.push(i);
+            inner(i+1, n, k, current, out);
+            current.pop();
+        }
+    }
+    let mut out = Vec::new();
+    inner(0, n, k, &mut Vec::new(), &mut out);
+    out
+}
+
+fn one_based(indexes:&[usize]) -> String {
+    indexes.iter().map(|i| (i+1).to_string()).collect::<Vec<String>>().join(",")
+}
@@ -0,0 +1,415 @@
+//-----------------------------------------------------------------------------
+// Transformations
+//   Digit relabeling, band/stack swaps, row/column swaps within a band or
+//   stack, and transposition all turn a puzzle into another valid puzzle with
+//   the same logic. Puzzles related this way are considered the same puzzle
+//-----------------------------------------------------------------------------
+
+use std::fmt;
+use crate::GameBoard;
+use crate::random::Rng;
+use crate::shape::Shape;
+
+/// A validity preserving transformation. Cell k of the result takes the value of cell `cells[k]`
+/// of the original, relabeled through `digits` (digits[0] is always 0)
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Transform {
+    pub cells: [usize;81],
+    pub digits: [usize;10],
+}
+impl Transform {
+    /// The transformation that changes nothing
+    pub fn identity() -> Transform {
+        let mut cells = [0;81];
+        for (k,cell) in cells.iter_mut().enumerate() {*cell = k;}
+        Transform{cells, digits:[0,1,2,3,4,5,6,7,8,9]}
+    }
+
+    /// Builds a transformation from an optional transpose followed by a new row and column order.
+    /// Row i of the result is row rows[i] of the (transposed) original, and the same for columns
+    pub fn from_layout(transpose:bool, rows:&[usize;9], cols:&[usize;9], digits:[usize;10]) -> Transform {
+        let mut cells = [0;81];
+        for i in 0..9 {
+            for j in 0..9 {
+                let (r,c) = (rows[i],cols[j]);
+                cells[i*9+j] = if transpose {c*9+r} else {r*9+c};
+            }
+        }
+        Transform{cells, digits}
+    }
+
+    /// Applies the transformation to 81 values
+    pub fn apply(&self, values:&[usize]) -> Vec<usize> {
+        self.cells.iter().map(|&k| self.digits[values[k]]).collect()
+    }
+
+    /// The transformation that does this one and then `next`
+    pub fn then(&self, next:&Transform) -> Transform {
+        let mut cells = [0;81];
+        for (k,cell) in cells.iter_mut().enumerate() {*cell = self.cells[next.cells[k]];}
+        let mut digits = [0;10];
+        for (d,digit) in digits.iter_mut().enumerate() {*digit = next.digits[self.digits[d]];}
+        Transform{cells, digits}
+    }
+
+    /// The transformation that undoes this one
+    pub fn inverse(&self) -> Transform {
+        let mut cells = [0;81];
+        for (k,&from) in self.cells.iter().enumerate() {cells[from] = k;}
+        let mut digits = [0;10];
+        for (d,&to) in self.digits.iter().enumerate() {digits[to] = d;}
+        Transform{cells, digits}
+    }
+}
+
+//-------------------------------------
+// Operations
+//   Named steps that can be chained into a transformation. A chain is written
+//   as space separated operations, e.g. "digits=912345678 bands=201 rot=90"
+//     digits=ABCDEFGHI   1 becomes A, 2 becomes B, ...
+//     bands=XYZ          band 1 of the result is band X+1 of the original, ...
+//     stacks=XYZ         the same for stacks
+//     rowsN=XYZ          order of the rows within band N (0 based)
+//     colsN=XYZ          order of the columns within stack N (0 based)
+//     rot=90|180|270     clockwise rotation
+//     mirror=h|v|d|a     top to bottom, left to right, main diagonal, or anti diagonal
+//     transpose          same as mirror=d
+//-------------------------------------
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Operation {
+    Digits([usize;9]),
+    Bands([usize;3]),
+    Stacks([usize;3]),
+    Rows(usize,[usize;3]),
+    Cols(usize,[usize;3]),
+    Rotate(usize),
+    Mirror(char),
+    Transpose,
+}
+impl Operation {
+    /// the transformation the operation performs
+    pub fn transform(&self) -> Transform {
+        let identity: [usize;9] = [0,1,2,3,4,5,6,7,8];
+        match *self {
+            Operation::Digits(map) => {
+                let mut digits = [0;10];
+                digits[1..].copy_from_slice(&map);
+                Transform{cells:Transform::identity().cells, digits}
+            }
+            Operation::Bands(order) => Transform::from_layout(false, &expand_bands(order), &identity, Transform::identity().digits),
+            Operation::Stacks(order) => Transform::from_layout(false, &identity, &expand_bands(order), Transform::identity().digits),
+            Operation::Rows(band,order) => Transform::from_layout(false, &expand_within(band,order), &identity, Transform::identity().digits),
+            Operation::Cols(stack,order) => Transform::from_layout(false, &identity, &expand_within(stack,order), Transform::identity().digits),
+            Operation::Rotate(degrees) => map_cells(|i,j| match degrees {
+                90 => (8-j,i),
+                180 => (8-i,8-j),
+                _ => (j,8-i),
+            }),
+            Operation::Mirror(axis) => map_cells(|i,j| match axis {
+                'h' => (8-i,j),
+                'v' => (i,8-j),
+                'd' => (j,i),
+                _ => (8-j,8-i),
+            }),
+            Operation::Transpose => map_cells(|i,j| (j,i)),
+        }
+    }
+
+    /// reads one operation written as described above
+    pub fn parse(text:&str) -> Result<Operation,String> {
+        if text == "transpose" {
+            return Ok(Operation::Transpose);
+        }
+        let (name,arg) = text.split_once('=').ok_or_else(|| format!("invalid operation '{}'",text))?;
+        let op = match name {
+            "digits" => {
+                let map = parse_order::<9>(arg, 1)?;
+                Operation::Digits(map.map(|d| d+1))
+            }
+            "bands" => Operation::Bands(parse_order::<3>(arg, 0)?),
+            "stacks" => Operation::Stacks(parse_order::<3>(arg, 0)?),
+            "rot" => match arg {
+                "90" | "180" | "270" => Operation::Rotate(arg.parse().unwrap()),
+                _ => return Err(format!("rotation must be 90, 180, or 270, not '{}'",arg)),
+            },
+            "mirror" => match arg {
+                "h" | "v" | "d" | "a" => Operation::Mirror(arg.chars().next().unwrap()),
+                _ => return Err(format!("mirror must be h, v, d, or a, not '{}'",arg)),
+            },
+            _ if name.len() == 5 && (name.starts_with("rows") || name.starts_with("cols")) => {
+                let index = match name[4..].parse::<usize>() {
+                    Ok(index) if index < 3 => index,
+                    _ => return Err(format!("invalid band or stack in '{}'",text)),
+                };
+                let order = parse_order::<3>(arg, 0)?;
+                if name.starts_with("rows") {Operation::Rows(index,order)} else {Operation::Cols(index,order)}
+            }
+            _ => return Err(format!("invalid operation '{}'",text)),
+        };
+        Ok(op)
+    }
+}
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |order:&[usize]| order.iter().map(|v| v.to_string()).collect::<String>();
+        match self {
+            Operation::Digits(map) => write!(f,"digits={}",join(map)),
+            Operation::Bands(order) => write!(f,"bands={}",join(order)),
+            Operation::Stacks(order) => write!(f,"stacks={}",join(order)),
+            Operation::Rows(band,order) => write!(f,"rows{}={}",band,join(order)),
+            Operation::Cols(stack,order) => write!(f,"cols{}={}",stack,join(order)),
+            Operation::Rotate(degrees) => write!(f,"rot={}",degrees),
+            Operation::Mirror(axis) => write!(f,"mirror={}",axis),
+            Operation::Transpose => write!(f,"transpose"),
+        }
+    }
+}
+
+/// reads a space separated chain of operations
+pub fn parse_chain(text:&str) -> Result<Vec<Operation>,String> {
+    text.split_whitespace().map(Operation::parse).collect()
+}
+
+/// writes a chain of operations in the form parse_chain reads
+pub fn chain_to_string(chain:&[Operation]) -> String {
+    chain.iter().map(|op| op.to_string()).collect::<Vec<String>>().join(" ")
+}
+
+/// the transformation that performs every operation of the chain in order
+pub fn chain_transform(chain:&[Operation]) -> Transform {
+    chain.iter().fold(Transform::identity(), |t,op| t.then(&op.transform()))
+}
+
+/// a random chain that relabels the digits, reorders bands, stacks, rows, and columns, then maybe rotates or mirrors
+pub fn random_chain(rng:&mut Rng) -> Vec<Operation> {
+    let mut digits = [1,2,3,4,5,6,7,8,9];
+    rng.shuffle(&mut digits);
+    let mut chain = vec![Operation::Digits(digits)];
+    let order = |rng:&mut Rng| {
+        let mut order = [0,1,2];
+        rng.shuffle(&mut order);
+        order
+    };
+    chain.push(Operation::Bands(order(rng)));
+    chain.push(Operation::Stacks(order(rng)));
+    for k in 0..3 {
+        chain.push(Operation::Rows(k,order(rng)));
+        chain.push(Operation::Cols(k,order(rng)));
+    }
+    match rng.below(8) {
+        0 => {}
+        1 => chain.push(Operation::Rotate(90)),
+        2 => chain.push(Operation::Rotate(180)),
+        3 => chain.push(Operation::Rotate(270)),
+        k => chain.push(Operation::Mirror(['h','v','d','a'][k-4])),
+    }
+    chain
+}
+
+/// turns a band (or stack) order into a row (or column) order
+fn expand_bands(order:[usize;3]) -> [usize;9] {
+    let mut returned = [0;9];
+    for (k,line) in returned.iter_mut().enumerate() {*line = order[k/3]*3 + k%3;}
+    returned
+}
+
+/// turns an order of the lines within one band (or stack) into a row (or column) order
+fn expand_within(band:usize, order:[usize;3]) -> [usize;9] {
+    let mut returned = [0,1,2,3,4,5,6,7,8];
+    for k in 0..3 {returned[band*3+k] = band*3 + order[k];}
+    returned
+}
+
+/// a transformation where cell (i,j) of the result takes the value of cell f(i,j) of the original
+fn map_cells(f:impl Fn(usize,usize) -> (usize,usize)) -> Transform {
+    let mut cells = [0;81];
+    for (k,cell) in cells.iter_mut().enumerate() {
+        let (r,c) = f(k/9, k%9);
+        *cell = r*9+c;
+    }
+    Transform{cells, digits:Transform::identity().digits}
+}
+
+/// reads N distinct digits, each in start..start+N, and returns them minus start
+fn parse_order<const N:usize>(text:&str, start:usize) -> Result<[usize;N],String> {
+    let values: Vec<usize> = text.chars().filter_map(|c| c.to_digit(10)).map(|d| d as usize).collect();
+    let mut seen = [false;N];
+    let mut returned = [0;N];
+    if values.len() != N || text.len() != N {
+        return Err(format!("'{}' must be {} digits",text,N));
+    }
+    for (k,&v) in values.iter().enumerate() {
+        if v < start || v >= start+N || seen[v-start] {
+            return Err(format!("'{}' must use each of {}..{} once",text,start,start+N-1));
+        }
+        seen[v-start] = true;
+        returned[k] = v-start;
+    }
+    Ok(returned)
+}
+
+//-------------------------------------
+// Canonical Form
+//   The lexicographically smallest 81 character string over every
+//   transformation. Two puzzles are the same puzzle exactly when their
+//   canonical forms match
+//-------------------------------------
+
+/// Every order of three things
+const PERMUTATIONS_3: [[usize;3];6] = [[0,1,2],[0,2,1],[1,0,2],[1,2,0],[2,0,1],[2,1,0]];
+
+/// Every row (or column) order that keeps rows inside their bands: a band order followed by an order within each band
+fn line_orders() -> Vec<[usize;9]> {
+    let mut returned = Vec::with_capacity(1296);
+    for bands in PERMUTATIONS_3 {
+        for a in PERMUTATIONS_3 {
+            for b in PERMUTATIONS_3 {
+                for c in PERMUTATIONS_3 {
+                    let within = [a,b,c];
+                    let mut order = [0;9];
+                    for (k,line) in order.iter_mut().enumerate() {
+                        *line = bands[k/3]*3 + within[k/3][k%3];
+                    }
+                    returned.push(order);
+                }
+            }
+        }
+    }
+    returned
+}
+
+/// Finds the canonical form of 81 values and the transformation that produces it
+pub fn canonical_form(values:&[usize]) -> (Vec<usize>, Transform) {
+    let orders = line_orders();
+    let mut best = [10usize;81]; // larger than any real string so the first layout always wins
+    let mut best_layout = (false, orders[0], orders[0], [0;10]);
+    for transpose in [false, true] {
+        let grid: Vec<usize> = (0..81).map(|k| if transpose {values[(k%9)*9 + k/9]} else {values[k]}).collect();
+        for rows in &orders {
+            for cols in &orders {
+                // relabel digits in the order they first appear, which gives the smallest string for this layout
+                let mut digits = [0usize;10];
+                let mut next = 1;
+                let mut better = false;
+                let mut candidate = [0usize;81];
+                let mut pruned = false;
+                for k in 0..81 {
+                    let v = grid[rows[k/9]*9 + cols[k%9]];
+                    if v != 0 && digits[v] == 0 {
+                        digits[v] = next;
+                        next += 1;
+                    }
+                    let mapped = digits[v];
+                    if !better {
+                        if mapped > best[k] {pruned = true; break;}
+                        if mapped < best[k] {better = true;}
+                    }
+                    candidate[k] = mapped;
+                }
+                if !pruned && better {
+                    // digits that never appear still need somewhere to go
+                    for digit in digits.iter_mut().skip(1) {
+                        if *digit == 0 {
+                            *digit = next;
+                            next += 1;
+                        }
+                    }
+                    best = candidate;
+                    best_layout = (transpose, *rows, *cols, digits);
+                }
+            }
+        }
+    }
+    let (transpose, rows, cols, digits) = best_layout;
+    (best.to_vec(), Transform::from_layout(transpose, &rows, &cols, digits))
+}
+
+impl GameBoard {
+    /// the values of every cell in board order, 0 for unsolved cells
+    pub fn values(&self) -> Vec<usize> {
+        self.shape.cells().map(|(i,j)| self.board[i][j].borrow().value).collect()
+    }
+
+    /// the transformations are only written for the classic board
+    fn require_classic(&self) {
+        if self.shape.size != 9 {
+            panic!("only classic 9x9 puzzles can be transformed, not {}x{}", self.shape.size, self.shape.size);
+        }
+        if self.shape != Shape::classic() {
+            panic!("only classic 9x9 puzzles can be transformed, not jigsaws or variants");
+        }
+    }
+
+    /// the canonical form of the board as an 81 character string
+    pub fn canonical_string(&self) -> String {
+        self.require_classic();
+        values_to_string(&canonical_form(&self.values()).0)
+    }
+
+    /// a new board holding the transformed values of this one
+    pub fn transformed(&self, transform:&Transform) -> GameBoard {
+        self.require_classic();
+        let mut returned = GameBoard::new();
+        returned.init_board_from_string(values_to_string(&transform.apply(&self.values())));
+        returned
+    }
+
+    /// checks if two boards are the same puzzle up to relabeling, swaps, and transposition
+    pub fn is_isomorphic(&self, other:&GameBoard) -> bool {
+        self.canonical_string() == other.canonical_string()
+    }
+}
+
+pub fn values_to_string(values:&[usize]) -> String {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLES: [&str; 3] = [
+        "002000063009000001006000400020180070900760000070490816000800007300040008008000940",
+        "091000203000002700705600000000713060009000000000500002000007304000060009000300015",
+        "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+    ];
+
+    fn parse(text:&str) -> Vec<usize> {
+        text.chars().map(|c| c.to_digit(10).unwrap() as usize).collect()
+    }
+
+    #[test]
+    fn canonical_form_ignores_random_chains() {
+        for puzzle in PUZZLES {
+            let values = parse(puzzle);
+            let (canonical, transform) = canonical_form(&values);
+            assert_eq!(transform.apply(&values), canonical);
+            for seed in 0..8 {
+                let chain = random_chain(&mut Rng::new(seed));
+                let moved = chain_transform(&chain).apply(&values);
+                assert_eq!(canonical_form(&moved).0, canonical, "chain {}", chain_to_string(&chain));
+            }
+        }
+    }
+
+    #[test]
+    fn then_inverse_is_identity() {
+        let values = parse(PUZZLES[0]);
+        for seed in 0..8 {
+            let mut rng = Rng::new(seed);
+            let first = chain_transform(&random_chain(&mut rng));
+            let second = chain_transform(&random_chain(&mut rng));
+            let both = first.then(&second);
+            assert_eq!(both.apply(&values), second.apply(&first.apply(&values)));
+            assert_eq!(both.then(&both.inverse()), Transform::identity());
+            assert_eq!(both.inverse().apply(&both.apply(&values)), values);
+        }
+    }
+
+    #[test]
+    fn chains_round_trip_through_text() {
+        let chain = random_chain(&mut Rng::new(3));
+        assert_eq!(parse_chain(&chain_to_string(&chain)).unwrap(), chain);
+    }
+}